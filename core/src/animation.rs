@@ -0,0 +1,648 @@
+use std::{time::Duration, collections::HashMap};
+
+use crate::{Color, CoordValue, BackgroundType, Matrix, TransformFunction, Shadow, FilterFunction, Style, transform_matrix, css::{Property, Value}};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    Ease,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    CubicBezier(f32, f32, f32, f32),
+    Spring { stiffness: f32, damping: f32, mass: f32 }
+}
+impl Easing {
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match *self {
+            Self::Linear => t,
+            Self::Ease => cubic_bezier(0.25, 0.1, 0.25, 1., t),
+            Self::EaseIn => cubic_bezier(0.42, 0., 1., 1., t),
+            Self::EaseOut => cubic_bezier(0., 0., 0.58, 1., t),
+            Self::EaseInOut => cubic_bezier(0.42, 0., 0.58, 1., t),
+            Self::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
+            Self::Spring { stiffness, damping, mass } => spring(stiffness, damping, mass, t)
+        }
+    }
+}
+
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, t: f32) -> f32 {
+    if t <= 0. || t >= 1. { return t }
+    let x1 = x1.clamp(0., 1.);
+    let x2 = x2.clamp(0., 1.);
+    let curve = |a: f32, b: f32, s: f32| 3. * a * s * (1. - s).powi(2) + 3. * b * s * s * (1. - s) + s * s * s;
+    let derivative = |a: f32, b: f32, s: f32| 3. * a * (1. - s).powi(2) + 6. * (b - a) * s * (1. - s) + 3. * (1. - b) * s * s;
+    let mut s = t;
+    for _ in 0..8 {
+        let dx = derivative(x1, x2, s);
+        if dx.abs() < 1e-6 { break }
+        s = (s - (curve(x1, x2, s) - t) / dx).clamp(0., 1.)
+    }
+    if (curve(x1, x2, s) - t).abs() > 1e-5 {
+        let (mut lo, mut hi) = (0f32, 1f32);
+        for _ in 0..32 {
+            s = (lo + hi) / 2.;
+            if curve(x1, x2, s) < t { lo = s } else { hi = s }
+        }
+    }
+    curve(y1, y2, s)
+}
+
+// The spring is simulated from rest and stretched so that it settles exactly at the end of the duration.
+fn spring(stiffness: f32, damping: f32, mass: f32, t: f32) -> f32 {
+    if t >= 1. { return 1. }
+    let mass = mass.max(1e-3);
+    let w0 = (stiffness.max(1e-3) / mass).sqrt();
+    let zeta = (damping / (2. * (stiffness.max(1e-3) * mass).sqrt())).max(1e-2);
+    if (zeta - 1.).abs() < 1e-3 {
+        let tau = t * 9.23 / w0;
+        return 1. - (-w0 * tau).exp() * (1. + w0 * tau)
+    }
+    if zeta < 1. {
+        let tau = t * 1000f32.ln() / (zeta * w0);
+        let wd = w0 * (1. - zeta * zeta).sqrt();
+        1. - (-zeta * w0 * tau).exp() * ((wd * tau).cos() + zeta * w0 / wd * (wd * tau).sin())
+    } else {
+        let root = (zeta * zeta - 1.).sqrt();
+        let r1 = -w0 * (zeta - root);
+        let r2 = -w0 * (zeta + root);
+        let tau = t * 1000f32.ln() / -r1;
+        1. - (r2 * (r1 * tau).exp() - r1 * (r2 * tau).exp()) / (r2 - r1)
+    }
+}
+
+pub trait Interpolate {
+    fn interpolate(&self, to: &Self, t: f32) -> Self;
+}
+impl Interpolate for f32 {
+    fn interpolate(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+impl Interpolate for Color {
+    fn interpolate(&self, to: &Self, t: f32) -> Self {
+        Self::new(self.r.interpolate(&to.r, t), self.g.interpolate(&to.g, t), self.b.interpolate(&to.b, t), self.a.interpolate(&to.a, t))
+    }
+}
+impl Interpolate for CoordValue {
+    fn interpolate(&self, to: &Self, t: f32) -> Self {
+        match (self, to) {
+            (Self::Px(a), Self::Px(b)) => Self::Px(a.interpolate(b, t)),
+            (Self::Perc(a), Self::Perc(b)) => Self::Perc(a.interpolate(b, t)),
+            _ => if t < 0.5 { *self } else { *to }
+        }
+    }
+}
+impl Interpolate for BackgroundType {
+    fn interpolate(&self, to: &Self, t: f32) -> Self {
+        match (self, to) {
+            (Self::Solid(a), Self::Solid(b)) => Self::Solid(a.interpolate(b, t)),
+            (Self::Solid(a), Self::None) => Self::Solid(a.interpolate(&Color { a: 0., ..*a }, t)),
+            (Self::None, Self::Solid(b)) => Self::Solid(Color { a: 0., ..*b }.interpolate(b, t)),
+            (Self::None, Self::None) => Self::None,
+            // An SVG cannot be blended with another background, so changes to or from one are discrete and
+            // switch at the midpoint, like non-interpolable values in CSS.
+            (Self::Svg(_), _) | (_, Self::Svg(_)) => if t < 0.5 { self.clone() } else { to.clone() }
+        }
+    }
+}
+impl Interpolate for Matrix {
+    fn interpolate(&self, to: &Self, t: f32) -> Self {
+        let (a, b) = (decompose(self), decompose(to));
+        let mut angle = b.2 - a.2;
+        if angle > std::f32::consts::PI { angle -= std::f32::consts::TAU }
+        if angle < -std::f32::consts::PI { angle += std::f32::consts::TAU }
+        Matrix::translate(a.0.interpolate(&b.0, t), a.1.interpolate(&b.1, t))
+            .multiply(&Matrix::rotate(a.2 + angle * t))
+            .multiply(&Matrix::new(1., 0., a.3.interpolate(&b.3, t), 1., 0., 0.))
+            .multiply(&Matrix::scale(a.4.interpolate(&b.4, t), a.5.interpolate(&b.5, t)))
+    }
+}
+
+// Splits a matrix into translation, rotation, shear and scale, in the order they are recomposed.
+fn decompose(m: &Matrix) -> (f32, f32, f32, f32, f32, f32) {
+    let (mut a, mut b, mut c, mut d) = (m.a, m.b, m.c, m.d);
+    let mut sx = (a * a + b * b).sqrt();
+    if sx == 0. { return (m.e, m.f, 0., 0., 0., 0.) }
+    a /= sx;
+    b /= sx;
+    let mut shear = a * c + b * d;
+    c -= a * shear;
+    d -= b * shear;
+    let sy = (c * c + d * d).sqrt();
+    if sy != 0. { shear /= sy }
+    if m.a * m.d - m.b * m.c < 0. {
+        a = -a;
+        b = -b;
+        sx = -sx;
+        shear = -shear
+    }
+    (m.e, m.f, b.atan2(a), shear, sx, sy)
+}
+
+impl Interpolate for TransformFunction {
+    fn interpolate(&self, to: &Self, t: f32) -> Self {
+        match (self, to) {
+            (Self::Translate(x0, y0), Self::Translate(x1, y1)) => Self::Translate(x0.interpolate(x1, t), y0.interpolate(y1, t)),
+            (Self::Scale(x0, y0), Self::Scale(x1, y1)) => Self::Scale(x0.interpolate(x1, t), y0.interpolate(y1, t)),
+            (Self::Rotate(a0), Self::Rotate(a1)) => Self::Rotate(a0.interpolate(a1, t)),
+            (Self::Skew(x0, y0), Self::Skew(x1, y1)) => Self::Skew(x0.interpolate(x1, t), y0.interpolate(y1, t)),
+            _ => Self::Matrix(self.to_matrix(0., 0.).interpolate(&to.to_matrix(0., 0.), t))
+        }
+    }
+}
+fn identity_like(f: &TransformFunction) -> TransformFunction {
+    match f {
+        TransformFunction::Translate(..) => TransformFunction::Translate(CoordValue::Px(0.), CoordValue::Px(0.)),
+        TransformFunction::Scale(..) => TransformFunction::Scale(1., 1.),
+        TransformFunction::Rotate(_) => TransformFunction::Rotate(0.),
+        TransformFunction::Skew(..) => TransformFunction::Skew(0., 0.),
+        TransformFunction::Matrix(_) => TransformFunction::Matrix(Matrix::IDENTITY)
+    }
+}
+fn has_percentage(functions: &[TransformFunction]) -> bool {
+    functions.iter().any(|f| matches!(f, TransformFunction::Translate(CoordValue::Perc(_), _) | TransformFunction::Translate(_, CoordValue::Perc(_))))
+}
+impl Interpolate for Vec<TransformFunction> {
+    fn interpolate(&self, to: &Self, t: f32) -> Self {
+        let len = self.len().max(to.len());
+        let from: Vec<_> = (0..len).map(|i| self.get(i).copied().unwrap_or_else(|| identity_like(&to[i]))).collect();
+        let to: Vec<_> = (0..len).map(|i| to.get(i).copied().unwrap_or_else(|| identity_like(&from[i]))).collect();
+        if from.iter().zip(to.iter()).all(|(a, b)| std::mem::discriminant(a) == std::mem::discriminant(b)) {
+            return from.iter().zip(to.iter()).map(|(a, b)| a.interpolate(b, t)).collect()
+        }
+        if has_percentage(&from) || has_percentage(&to) {
+            return if t < 0.5 { from } else { to }
+        }
+        vec![TransformFunction::Matrix(transform_matrix(&from, 0., 0.).interpolate(&transform_matrix(&to, 0., 0.), t))]
+    }
+}
+
+impl Interpolate for Shadow {
+    fn interpolate(&self, to: &Self, t: f32) -> Self {
+        Self {
+            x: self.x.interpolate(&to.x, t),
+            y: self.y.interpolate(&to.y, t),
+            blur: self.blur.interpolate(&to.blur, t),
+            spread: self.spread.interpolate(&to.spread, t),
+            color: self.color.interpolate(&to.color, t),
+            inset: if t < 0.5 { self.inset } else { to.inset }
+        }
+    }
+}
+impl Interpolate for Vec<Shadow> {
+    fn interpolate(&self, to: &Self, t: f32) -> Self {
+        let none = |s: &Shadow| Shadow { x: 0., y: 0., blur: 0., spread: 0., color: Color { a: 0., ..s.color }, inset: s.inset };
+        (0..self.len().max(to.len())).map(|i| match (self.get(i), to.get(i)) {
+            (Some(a), Some(b)) => a.interpolate(b, t),
+            (Some(a), None) => a.interpolate(&none(a), t),
+            (None, Some(b)) => none(b).interpolate(b, t),
+            (None, None) => unreachable!()
+        }).collect()
+    }
+}
+impl Interpolate for Vec<FilterFunction> {
+    fn interpolate(&self, to: &Self, t: f32) -> Self {
+        (0..self.len().max(to.len())).map(|i| {
+            let FilterFunction::Blur(a) = self.get(i).copied().unwrap_or(FilterFunction::Blur(0.));
+            let FilterFunction::Blur(b) = to.get(i).copied().unwrap_or(FilterFunction::Blur(0.));
+            FilterFunction::Blur(a.interpolate(&b, t))
+        }).collect()
+    }
+}
+
+impl Interpolate for Value {
+    fn interpolate(&self, to: &Self, t: f32) -> Self {
+        match (self, to) {
+            (Self::Number(a), Self::Number(b)) => Self::Number(a.interpolate(b, t)),
+            (Self::Coord(a), Self::Coord(b)) => Self::Coord(a.interpolate(b, t)),
+            (Self::Background(a), Self::Background(b)) => Self::Background(a.interpolate(b, t)),
+            (Self::Transform(a), Self::Transform(b)) => Self::Transform(a.interpolate(b, t)),
+            (Self::Origin(x0, y0), Self::Origin(x1, y1)) => Self::Origin(x0.interpolate(x1, t), y0.interpolate(y1, t)),
+            (Self::Shadows(a), Self::Shadows(b)) => Self::Shadows(a.interpolate(b, t)),
+            (Self::Filter(a), Self::Filter(b)) => Self::Filter(a.interpolate(b, t)),
+            _ => if t < 0.5 { self.clone() } else { to.clone() }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transition {
+    pub property: Property,
+    pub duration: Duration,
+    pub easing: Easing,
+    pub delay: Duration
+}
+impl Transition {
+    pub fn new(property: Property, duration: Duration) -> Self {
+        Self { property, duration, easing: Easing::Ease, delay: Duration::ZERO }
+    }
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Iterations {
+    Count(f32),
+    Infinite
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Direction {
+    #[default]
+    Normal,
+    Reverse,
+    Alternate,
+    AlternateReverse
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FillMode {
+    #[default]
+    None,
+    Forwards,
+    Backwards,
+    Both
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    pub offset: f32,
+    pub values: Vec<(Property, Value)>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animation {
+    pub keyframes: Vec<Keyframe>,
+    pub duration: Duration,
+    pub easing: Easing,
+    pub delay: Duration,
+    pub iterations: Iterations,
+    pub direction: Direction,
+    pub fill_mode: FillMode
+}
+impl Animation {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            keyframes: Vec::new(),
+            duration,
+            easing: Easing::Ease,
+            delay: Duration::ZERO,
+            iterations: Iterations::Count(1.),
+            direction: Direction::Normal,
+            fill_mode: FillMode::None
+        }
+    }
+    pub fn keyframe(mut self, offset: f32, values: &[(Property, Value)]) -> Self {
+        let offset = offset.clamp(0., 1.);
+        match self.keyframes.iter_mut().find(|k| k.offset == offset) {
+            Some(k) => k.values.extend_from_slice(values),
+            None => {
+                let i = self.keyframes.iter().position(|k| k.offset > offset).unwrap_or(self.keyframes.len());
+                self.keyframes.insert(i, Keyframe { offset, values: values.to_vec() })
+            }
+        }
+        self
+    }
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+    pub fn iterations(mut self, iterations: Iterations) -> Self {
+        self.iterations = iterations;
+        self
+    }
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+    pub fn fill_mode(mut self, fill_mode: FillMode) -> Self {
+        self.fill_mode = fill_mode;
+        self
+    }
+
+    pub fn properties(&self) -> Vec<Property> {
+        let mut res = Vec::new();
+        for (property, _) in self.keyframes.iter().flat_map(|k| k.values.iter()) {
+            if !res.contains(property) { res.push(*property) }
+        }
+        res
+    }
+
+    fn is_reversed(&self, iteration: u32) -> bool {
+        match self.direction {
+            Direction::Normal => false,
+            Direction::Reverse => true,
+            Direction::Alternate => iteration % 2 == 1,
+            Direction::AlternateReverse => iteration % 2 == 0
+        }
+    }
+
+    // Returns the progress within the current iteration, or None when the animation has no effect at this time.
+    fn progress(&self, elapsed: f32) -> (Option<f32>, bool) {
+        let duration = self.duration.as_secs_f32();
+        let delay = self.delay.as_secs_f32();
+        let fill_backwards = matches!(self.fill_mode, FillMode::Backwards | FillMode::Both);
+        let fill_forwards = matches!(self.fill_mode, FillMode::Forwards | FillMode::Both);
+        if elapsed < delay {
+            let p = if self.is_reversed(0) { 1. } else { 0. };
+            return (fill_backwards.then_some(p), false)
+        }
+        let active = elapsed - delay;
+        let count = match self.iterations {
+            Iterations::Count(count) => count.max(0.),
+            Iterations::Infinite => f32::INFINITY
+        };
+        // A zero duration makes the active period empty, even with infinite iterations.
+        if duration <= 0. || active >= duration * count {
+            let (last, p) = if count.is_finite() {
+                ((count.ceil() as u32).max(1) - 1, if count > 0. && count.fract() == 0. { 1. } else { count.fract() })
+            } else {
+                (0, 1.)
+            };
+            let p = if self.is_reversed(last) { 1. - p } else { p };
+            return (fill_forwards.then_some(p), true)
+        }
+        let iteration = (active / duration).floor();
+        let p = active / duration - iteration;
+        (Some(if self.is_reversed(iteration as u32) { 1. - p } else { p }), false)
+    }
+
+    fn sample(&self, property: Property, base: &Value, progress: f32) -> Value {
+        let mut frames: Vec<(f32, Value)> = self.keyframes.iter()
+            .filter_map(|k| k.values.iter().rev().find(|(p, _)| *p == property).map(|(_, v)| (k.offset, v.clone())))
+            .collect();
+        if frames.first().map_or(true, |(o, _)| *o > 0.) { frames.insert(0, (0., base.clone())) }
+        if frames.last().map_or(true, |(o, _)| *o < 1.) { frames.push((1., base.clone())) }
+        let i = frames.iter().rposition(|(o, _)| *o <= progress).unwrap_or(0).min(frames.len() - 2);
+        let (o0, v0) = &frames[i];
+        let (o1, v1) = &frames[i + 1];
+        let t = if o1 > o0 { (progress - o0) / (o1 - o0) } else { 1. };
+        v0.interpolate(v1, self.easing.apply(t))
+    }
+}
+
+struct RunningTransition {
+    transition: Transition,
+    from: Value,
+    to: Value,
+    start: Option<Duration>
+}
+
+struct RunningAnimation {
+    name: Option<String>,
+    animation: Animation,
+    base: Vec<(Property, Value)>,
+    start: Option<Duration>
+}
+
+#[derive(Default)]
+pub(crate) struct AnimationState {
+    transitions: Vec<Transition>,
+    last: HashMap<Property, Value>,
+    running_transitions: Vec<RunningTransition>,
+    running_animations: Vec<RunningAnimation>,
+    css_transitions: Vec<Property>,
+    css_animations: Vec<String>,
+    // The last value set by a transition or animation, so the cascade can tell it apart from a script's.
+    written: HashMap<Property, Value>
+}
+impl AnimationState {
+    pub fn set_transition(&mut self, transition: Transition) {
+        self.transitions.retain(|t| t.property != transition.property);
+        self.transitions.push(transition)
+    }
+    pub fn remove_transition(&mut self, property: Property) {
+        self.transitions.retain(|t| t.property != property);
+        self.running_transitions.retain(|t| t.transition.property != property);
+        self.last.remove(&property);
+    }
+    pub fn get_transitions(&self) -> Vec<Transition> {
+        self.transitions.clone()
+    }
+    pub fn animate(&mut self, style: &Style, animation: Animation) {
+        self.start(style, None, animation)
+    }
+    fn start(&mut self, style: &Style, name: Option<String>, animation: Animation) {
+        let base = animation.properties().into_iter().map(|p| (p, p.get(style))).collect();
+        self.running_animations.push(RunningAnimation { name, animation, base, start: None })
+    }
+    pub fn cancel_animations(&mut self, style: &Style) {
+        self.cancel(style, |_| true)
+    }
+    fn cancel(&mut self, style: &Style, f: impl Fn(&RunningAnimation) -> bool) {
+        let (cancelled, running) = std::mem::take(&mut self.running_animations).into_iter().partition(f);
+        self.running_animations = running;
+        for running in cancelled {
+            for (property, value) in running.base.iter() {
+                property.set(style, value);
+                self.last.insert(*property, value.clone());
+                self.written.insert(*property, value.clone());
+            }
+        }
+    }
+    // Replaces what the previous cascade declared. Animations only start when their name is added and are
+    // cancelled when it is removed, so a restyle does not restart them.
+    pub fn set_css(&mut self, style: &Style, transitions: Vec<Transition>, animations: Vec<(String, Animation)>) -> bool {
+        for property in std::mem::take(&mut self.css_transitions) {
+            if !transitions.iter().any(|t| t.property == property) { self.remove_transition(property) }
+        }
+        for transition in transitions {
+            self.css_transitions.push(transition.property);
+            if !self.transitions.contains(&transition) { self.set_transition(transition) }
+        }
+
+        let names: Vec<String> = animations.iter().map(|(name, _)| name.clone()).collect();
+        self.cancel(style, |r| r.name.as_ref().is_some_and(|name| !names.contains(name)));
+        let mut started = false;
+        for (name, animation) in animations {
+            if !self.css_animations.contains(&name) {
+                self.start(style, Some(name), animation);
+                started = true
+            }
+        }
+        self.css_animations = names;
+        started
+    }
+    pub fn get_written(&self, property: Property) -> Option<Value> {
+        self.written.get(&property).cloned()
+    }
+    pub fn is_animating(&self) -> bool {
+        !self.running_transitions.is_empty() || !self.running_animations.is_empty()
+    }
+
+    pub fn tick(&mut self, style: &Style, now: Duration) -> bool {
+        if self.transitions.is_empty() && !self.is_animating() { return false }
+
+        for transition in self.transitions.iter() {
+            let property = transition.property;
+            let current = property.get(style);
+            let last = match self.last.get(&property) {
+                Some(v) => v.clone(),
+                None => { self.last.insert(property, current); continue }
+            };
+            if last == current { continue }
+            let running = self.running_transitions.iter().position(|r| r.transition.property == property);
+            if let Some(i) = running {
+                if self.running_transitions[i].to == current {
+                    property.set(style, &last);
+                    continue
+                }
+                self.running_transitions.remove(i);
+            }
+            property.set(style, &last);
+            self.running_transitions.push(RunningTransition { transition: *transition, from: last, to: current, start: None })
+        }
+
+        self.running_transitions.retain_mut(|running| {
+            let start = *running.start.get_or_insert(now);
+            let transition = &running.transition;
+            let elapsed = now.saturating_sub(start).saturating_sub(transition.delay).as_secs_f32();
+            let duration = transition.duration.as_secs_f32();
+            let t = if duration > 0. { (elapsed / duration).min(1.) } else { 1. };
+            let value = running.from.interpolate(&running.to, transition.easing.apply(t));
+            let value = if t >= 1. { running.to.clone() } else { value };
+            transition.property.set(style, &value);
+            self.written.insert(transition.property, value.clone());
+            self.last.insert(transition.property, value);
+            t < 1.
+        });
+
+        self.running_animations.retain_mut(|running| {
+            let start = *running.start.get_or_insert(now);
+            let elapsed = now.saturating_sub(start).as_secs_f32();
+            let (progress, finished) = running.animation.progress(elapsed);
+            for (property, base) in running.base.iter() {
+                let value = match progress {
+                    Some(p) => running.animation.sample(*property, base, p),
+                    None => base.clone()
+                };
+                property.set(style, &value);
+                self.written.insert(*property, value.clone());
+                if self.last.contains_key(property) { self.last.insert(*property, value); }
+            }
+            !finished
+        });
+
+        self.is_animating()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Svg;
+    use std::sync::Arc;
+
+    #[test]
+    fn backgrounds() {
+        let red = Color { r: 1., g: 0., b: 0., a: 1. };
+        let svg = BackgroundType::Svg(Arc::new(Svg::parse(r#"<svg xmlns="http://www.w3.org/2000/svg"/>"#).unwrap()));
+        let solid = BackgroundType::Solid(red);
+        assert_eq!(solid.interpolate(&BackgroundType::None, 0.25), BackgroundType::Solid(Color { a: 0.75, ..red }));
+        assert_eq!(BackgroundType::None.interpolate(&solid, 0.5), BackgroundType::Solid(Color { a: 0.5, ..red }));
+        for (from, to) in [(&solid, &svg), (&svg, &solid), (&svg, &BackgroundType::None)] {
+            assert_eq!(from.interpolate(to, 0.49), *from);
+            assert_eq!(from.interpolate(to, 0.5), *to);
+        }
+    }
+
+    fn animation(iterations: Iterations, direction: Direction, fill_mode: FillMode) -> Animation {
+        Animation::new(Duration::from_secs(1)).delay(Duration::from_secs(1)).iterations(iterations).direction(direction).fill_mode(fill_mode)
+    }
+
+    #[test]
+    fn directions() {
+        let cases = [
+            (Direction::Normal, [0.25, 0.25, 0.25]),
+            (Direction::Reverse, [0.75, 0.75, 0.75]),
+            (Direction::Alternate, [0.25, 0.75, 0.25]),
+            (Direction::AlternateReverse, [0.75, 0.25, 0.75])
+        ];
+        for (direction, expected) in cases {
+            let a = animation(Iterations::Infinite, direction, FillMode::None);
+            for (i, p) in expected.into_iter().enumerate() {
+                assert_eq!(a.progress(1.25 + i as f32), (Some(p), false), "{direction:?} iteration {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn iteration_counts() {
+        let cases = [
+            (Iterations::Count(1.), Direction::Normal, Some(1.)),
+            (Iterations::Count(2.), Direction::Alternate, Some(0.)),
+            (Iterations::Count(1.5), Direction::Normal, Some(0.5)),
+            (Iterations::Count(1.25), Direction::Alternate, Some(0.75)),
+            (Iterations::Count(0.), Direction::Normal, Some(0.)),
+            (Iterations::Count(0.), Direction::Reverse, Some(1.))
+        ];
+        for (iterations, direction, end) in cases {
+            let a = animation(iterations, direction, FillMode::Forwards);
+            assert_eq!(a.progress(100.), (end, true), "{iterations:?} {direction:?}");
+        }
+        let a = animation(Iterations::Count(1.5), Direction::Normal, FillMode::None);
+        assert_eq!(a.progress(2.25), (Some(0.25), false));
+        assert_eq!(a.progress(2.5), (None, true));
+    }
+
+    #[test]
+    fn fill_modes() {
+        let cases = [
+            (FillMode::None, None, None),
+            (FillMode::Forwards, None, Some(1.)),
+            (FillMode::Backwards, Some(0.), None),
+            (FillMode::Both, Some(0.), Some(1.))
+        ];
+        for (fill_mode, before, after) in cases {
+            let a = animation(Iterations::Count(1.), Direction::Normal, fill_mode);
+            assert_eq!(a.progress(0.5), (before, false), "{fill_mode:?}");
+            assert_eq!(a.progress(2.5), (after, true), "{fill_mode:?}");
+        }
+        let a = animation(Iterations::Count(1.), Direction::Reverse, FillMode::Both);
+        assert_eq!(a.progress(0.5), (Some(1.), false));
+        assert_eq!(a.progress(2.5), (Some(0.), true));
+    }
+
+    #[test]
+    fn zero_duration() {
+        for iterations in [Iterations::Count(3.), Iterations::Infinite] {
+            let a = Animation::new(Duration::ZERO).iterations(iterations).fill_mode(FillMode::Forwards);
+            assert_eq!(a.progress(0.), (Some(1.), true), "{iterations:?}");
+        }
+        let a = Animation::new(Duration::ZERO).iterations(Iterations::Infinite);
+        assert_eq!(a.progress(0.), (None, true));
+    }
+
+    #[test]
+    fn easings() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-3;
+        for easing in [Easing::Linear, Easing::Ease, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut, Easing::CubicBezier(0.3, -0.5, 0.7, 1.5)] {
+            assert_eq!(easing.apply(0.), 0., "{easing:?}");
+            assert_eq!(easing.apply(1.), 1., "{easing:?}");
+            assert_eq!(easing.apply(-1.), 0., "{easing:?}");
+        }
+        assert!(close(Easing::Linear.apply(0.3), 0.3));
+        assert!(close(Easing::EaseInOut.apply(0.5), 0.5));
+        assert!(close(Easing::CubicBezier(0., 0., 1., 1.).apply(0.3), 0.3));
+        assert!(Easing::EaseIn.apply(0.25) < 0.25);
+        assert!(Easing::EaseOut.apply(0.25) > 0.25);
+        assert!(close(Easing::Ease.apply(0.5), 0.8024));
+        assert!(Easing::CubicBezier(0.3, -0.5, 0.7, 1.5).apply(0.1) < 0.);
+        let spring = Easing::Spring { stiffness: 100., damping: 5., mass: 1. };
+        assert!((0..=10).any(|i| spring.apply(i as f32 / 10.) > 1.));
+        assert_eq!(spring.apply(1.), 1.);
+    }
+}
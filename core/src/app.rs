@@ -1,48 +1,369 @@
-use winit::{event_loop::{EventLoop, ControlFlow}, event::{Event, WindowEvent, KeyboardInput, ElementState}};
+use std::any::Any;
+use winit::{event_loop::{EventLoop, EventLoopBuilder, EventLoopProxy, ControlFlow}, event::{Event, WindowEvent, KeyboardInput, ElementState}};
+use winit::{window::{Window, WindowBuilder, WindowLevel, Icon}, event_loop::EventLoopWindowTarget, dpi::{PhysicalSize, PhysicalPosition}};
 
-use crate::Context;
+use wgpu::{Backends, PowerPreference, Features, Limits};
+
+use crate::{Context, Error, SurfaceSettings, AlphaMode};
+
+#[derive(Clone, Debug)]
+pub struct AppConfig {
+    pub backends: Backends,
+    pub power_preference: PowerPreference,
+    pub fallback_adapter: bool,
+    pub features: Features,
+    pub limits: Limits,
+    pub surface: SurfaceSettings
+}
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            backends: Backends::all(),
+            power_preference: PowerPreference::HighPerformance,
+            fallback_adapter: false,
+            features: Features::empty(),
+            limits: Limits::default(),
+            surface: SurfaceSettings::default()
+        }
+    }
+}
+impl AppConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn backends(mut self, backends: Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+    pub fn power_preference(mut self, power_preference: PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+    pub fn fallback_adapter(mut self, fallback_adapter: bool) -> Self {
+        self.fallback_adapter = fallback_adapter;
+        self
+    }
+    pub fn features(mut self, features: Features) -> Self {
+        self.features = features;
+        self
+    }
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+    pub fn surface(mut self, surface: SurfaceSettings) -> Self {
+        self.surface = surface;
+        self
+    }
+}
+
+pub(crate) enum AppEvent {
+    Wake,
+    User(Box<dyn Any + Send>)
+}
+
+// Posts events from any thread to the `on_user_event` hook of every script.
+#[derive(Clone)]
+pub struct EventSender {
+    proxy: EventLoopProxy<AppEvent>
+}
+impl EventSender {
+    pub(crate) fn new(proxy: EventLoopProxy<AppEvent>) -> Self {
+        Self { proxy }
+    }
+    // Returns false once the event loop has exited.
+    pub fn send<T: Any + Send>(&self, event: T) -> bool {
+        self.proxy.send_event(AppEvent::User(Box::new(event))).is_ok()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fullscreen {
+    Borderless,
+    Exclusive
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct WindowOptions {
+    window: WindowBuilder,
+    fullscreen: Option<Fullscreen>,
+    surface: SurfaceSettings
+}
+impl WindowOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.window = self.window.with_title(title);
+        self
+    }
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.window = self.window.with_inner_size(PhysicalSize::new(width, height));
+        self
+    }
+    pub fn min_size(mut self, width: u32, height: u32) -> Self {
+        self.window = self.window.with_min_inner_size(PhysicalSize::new(width, height));
+        self
+    }
+    pub fn max_size(mut self, width: u32, height: u32) -> Self {
+        self.window = self.window.with_max_inner_size(PhysicalSize::new(width, height));
+        self
+    }
+    pub fn position(mut self, x: i32, y: i32) -> Self {
+        self.window = self.window.with_position(PhysicalPosition::new(x, y));
+        self
+    }
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.window = self.window.with_resizable(resizable);
+        self
+    }
+    pub fn decorations(mut self, decorations: bool) -> Self {
+        self.window = self.window.with_decorations(decorations);
+        self
+    }
+    pub fn fullscreen(mut self, fullscreen: Option<Fullscreen>) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+    pub fn always_on_top(mut self, always_on_top: bool) -> Self {
+        let level = if always_on_top { WindowLevel::AlwaysOnTop } else { WindowLevel::Normal };
+        self.window = self.window.with_window_level(level);
+        self
+    }
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        set_transparent(&mut self.surface, transparent);
+        self
+    }
+    pub fn icon(mut self, icon: Icon) -> Self {
+        self.window = self.window.with_window_icon(Some(icon));
+        self
+    }
+    pub fn surface(mut self, surface: SurfaceSettings) -> Self {
+        self.surface = surface;
+        self
+    }
+    pub(crate) fn get_surface(&self) -> &SurfaceSettings {
+        &self.surface
+    }
+    pub(crate) fn build(&self, target: &EventLoopWindowTarget<AppEvent>) -> Result<Window, Error> {
+        let monitor = target.primary_monitor();
+        let fullscreen = match self.fullscreen {
+            Some(Fullscreen::Borderless) => Some(winit::window::Fullscreen::Borderless(monitor)),
+            Some(Fullscreen::Exclusive) => {
+                let mode = monitor.and_then(|monitor| monitor.video_modes().max_by_key(|mode| {
+                    (mode.size().width * mode.size().height, mode.refresh_rate_millihertz(), mode.bit_depth())
+                }));
+                if mode.is_none() { log::warn!("No video mode available for exclusive fullscreen, using borderless") }
+                Some(mode.map_or(winit::window::Fullscreen::Borderless(None), winit::window::Fullscreen::Exclusive))
+            }
+            None => None
+        };
+        Ok(self.window.clone()
+            .with_fullscreen(fullscreen)
+            .with_transparent(self.surface.alpha_mode != AlphaMode::Opaque)
+            .build(target)?)
+    }
+}
+
+// Transparency is composited by the surface, so it selects a premultiplied alpha mode unless another one is set.
+fn set_transparent(surface: &mut SurfaceSettings, transparent: bool) {
+    match (transparent, surface.alpha_mode) {
+        (true, AlphaMode::Opaque) => surface.alpha_mode = AlphaMode::PreMultiplied,
+        (false, _) => surface.alpha_mode = AlphaMode::Opaque,
+        _ => {}
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AppBuilder {
+    config: AppConfig,
+    window: WindowOptions
+}
+impl AppBuilder {
+    pub fn config(mut self, config: AppConfig) -> Self {
+        self.config = config;
+        self
+    }
+    pub fn window(mut self, window: WindowOptions) -> Self {
+        self.window = window;
+        self
+    }
+    fn map_window(mut self, f: impl FnOnce(WindowOptions) -> WindowOptions) -> Self {
+        self.window = f(self.window);
+        self
+    }
+    pub fn title(self, title: impl Into<String>) -> Self {
+        self.map_window(|w| w.title(title))
+    }
+    pub fn size(self, width: u32, height: u32) -> Self {
+        self.map_window(|w| w.size(width, height))
+    }
+    pub fn min_size(self, width: u32, height: u32) -> Self {
+        self.map_window(|w| w.min_size(width, height))
+    }
+    pub fn max_size(self, width: u32, height: u32) -> Self {
+        self.map_window(|w| w.max_size(width, height))
+    }
+    pub fn position(self, x: i32, y: i32) -> Self {
+        self.map_window(|w| w.position(x, y))
+    }
+    pub fn resizable(self, resizable: bool) -> Self {
+        self.map_window(|w| w.resizable(resizable))
+    }
+    pub fn decorations(self, decorations: bool) -> Self {
+        self.map_window(|w| w.decorations(decorations))
+    }
+    pub fn fullscreen(self, fullscreen: Option<Fullscreen>) -> Self {
+        self.map_window(|w| w.fullscreen(fullscreen))
+    }
+    pub fn always_on_top(self, always_on_top: bool) -> Self {
+        self.map_window(|w| w.always_on_top(always_on_top))
+    }
+    // The main window uses the surface settings of the config.
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        set_transparent(&mut self.config.surface, transparent);
+        self
+    }
+    pub fn icon(self, icon: Icon) -> Self {
+        self.map_window(|w| w.icon(icon))
+    }
+    pub fn build(self) -> App {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+    pub fn try_build(self) -> Result<App, Error> {
+        let event_loop = EventLoopBuilder::with_user_event().build();
+        let window = self.window.surface(self.config.surface).build(&event_loop)?;
+        Ok(App {
+            context: Context::try_new(window, &self.config, event_loop.create_proxy())?,
+            event_loop
+        })
+    }
+}
 
 pub struct App {
-    event_loop: EventLoop<()>,
+    event_loop: EventLoop<AppEvent>,
     pub context: Context
 }
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl App {
     pub fn new() -> Self {
-        let event_loop = EventLoop::new();
-        Self {
-            context: Context::new(&event_loop),
-            event_loop
-        }
+        Self::with_config(AppConfig::default())
+    }
+    pub fn with_config(config: AppConfig) -> Self {
+        Self::builder().config(config).build()
+    }
+    pub fn try_new() -> Result<Self, Error> {
+        Self::try_with_config(AppConfig::default())
+    }
+    pub fn try_with_config(config: AppConfig) -> Result<Self, Error> {
+        Self::builder().config(config).try_build()
+    }
+    pub fn builder() -> AppBuilder {
+        AppBuilder::default()
     }
     pub fn run(self) {
-        let c = self.context;
-        c.setup();
-        self.event_loop.run(move |event, _, control_flow| {
+        let main = self.context;
+        main.setup();
+        let mut windows = vec![main.clone()];
+        self.event_loop.run(move |event, target, control_flow| {
             match event {
-                Event::WindowEvent { event, .. } => match event {
-                    WindowEvent::KeyboardInput {
-                        input: KeyboardInput {
-                            virtual_keycode: Some(key),
-                            state: ElementState::Pressed, ..
-                        }, ..
-                    } => c.key_pressed(key),
-                    WindowEvent::KeyboardInput {
-                        input: KeyboardInput {
-                            virtual_keycode: Some(key),
-                            state: ElementState::Released, ..
-                        }, ..
-                    } => c.key_released(&key),
-                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                    WindowEvent::Resized(new_size) => c.resize(new_size),
-                    _ => {}
-                },
-                Event::MainEventsCleared => c.request_redraw(),
-                Event::RedrawRequested(_) => {
-                    if !c.is_running() { return *control_flow = ControlFlow::Exit }
-                    c.update()
+                Event::WindowEvent { window_id, event } => {
+                    let c = match windows.iter().find(|c| c.window.id() == window_id) { Some(v) => v, None => return };
+                    // Resizes and style changes request their own frames, so moving the mouse over
+                    // elements without listeners does not redraw in on-demand mode.
+                    let changed = match event {
+                        WindowEvent::KeyboardInput {
+                            input: KeyboardInput {
+                                virtual_keycode: Some(key),
+                                state: ElementState::Pressed, ..
+                            }, ..
+                        } => c.key_pressed(key),
+                        WindowEvent::KeyboardInput {
+                            input: KeyboardInput {
+                                virtual_keycode: Some(key),
+                                state: ElementState::Released, ..
+                            }, ..
+                        } => c.key_released(&key),
+                        WindowEvent::CursorMoved { position, .. } => c.cursor_moved(position.x as f32, position.y as f32),
+                        WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } => c.mouse_pressed(button),
+                        WindowEvent::MouseInput { state: ElementState::Released, button, .. } => c.mouse_released(button),
+                        WindowEvent::CloseRequested => {
+                            if c.close_requested() { c.close_window() }
+                            true
+                        }
+                        WindowEvent::Resized(new_size) => {
+                            c.resize(new_size);
+                            false
+                        }
+                        WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => {
+                            c.scale_factor_changed(scale_factor);
+                            c.resize(*new_inner_size);
+                            false
+                        }
+                        WindowEvent::Focused(focused) => {
+                            c.focus_changed(focused);
+                            true
+                        }
+                        WindowEvent::DroppedFile(path) => {
+                            c.file_dropped(path);
+                            true
+                        }
+                        _ => false
+                    };
+                    if changed { c.request_frame() }
+                }
+                Event::MainEventsCleared => {
+                    for (options, setup) in main.take_pending_windows() {
+                        match options.build(target).and_then(|window| main.try_open(window, options.get_surface())) {
+                            Ok(c) => {
+                                setup(Ok(&c));
+                                c.setup();
+                                windows.push(c)
+                            }
+                            Err(e) => setup(Err(e))
+                        }
+                    }
+                    // The app exits once its last window is closed.
+                    windows.retain(|c| {
+                        if !c.is_window_open() { c.exited() }
+                        c.is_window_open()
+                    });
+                    if windows.is_empty() { return *control_flow = ControlFlow::Exit }
+                    for c in windows.iter().filter(|c| c.needs_redraw()) {
+                        c.request_redraw()
+                    }
+                }
+                Event::RedrawRequested(window_id) => {
+                    if !main.is_running() { return *control_flow = ControlFlow::Exit }
+                    if let Some(c) = windows.iter().find(|c| c.window.id() == window_id) { c.update() }
                 },
+                Event::UserEvent(AppEvent::Wake) if main.run_tasks() => windows.iter().for_each(|c| c.request_frame()),
+                Event::UserEvent(AppEvent::User(event)) => windows.iter().for_each(|c| {
+                    c.user_event(&*event);
+                    c.request_frame()
+                }),
+                Event::Suspended => windows.iter().for_each(|c| c.suspended()),
+                Event::Resumed => windows.iter().for_each(|c| c.resumed()),
+                Event::LoopDestroyed => windows.iter().for_each(|c| c.exited()),
+                Event::RedrawEventsCleared if *control_flow != ControlFlow::Exit => {
+                    *control_flow = windows.iter().map(|c| c.control_flow()).fold(ControlFlow::Wait, earliest)
+                }
                 _ => {}
             }
         })
     }
-}
\ No newline at end of file
+}
+
+fn earliest(a: ControlFlow, b: ControlFlow) -> ControlFlow {
+    match (a, b) {
+        (ControlFlow::ExitWithCode(code), _) | (_, ControlFlow::ExitWithCode(code)) => ControlFlow::ExitWithCode(code),
+        (ControlFlow::Poll, _) | (_, ControlFlow::Poll) => ControlFlow::Poll,
+        (ControlFlow::WaitUntil(a), ControlFlow::WaitUntil(b)) => ControlFlow::WaitUntil(a.min(b)),
+        (ControlFlow::WaitUntil(at), _) | (_, ControlFlow::WaitUntil(at)) => ControlFlow::WaitUntil(at),
+        _ => ControlFlow::Wait
+    }
+}
@@ -0,0 +1,623 @@
+use std::{ops::Deref, f32::consts::{PI, FRAC_PI_2}};
+
+use crate::{Element, Color, Matrix, Rect};
+
+type Point = (f32, f32);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Segment {
+    MoveTo(Point),
+    LineTo(Point),
+    QuadTo(Point, Point),
+    CubicTo(Point, Point, Point),
+    Close
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+    segments: Vec<Segment>,
+    start: Option<Point>,
+    current: Option<Point>
+}
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn move_to(mut self, x: f32, y: f32) -> Self {
+        self.segments.push(Segment::MoveTo((x, y)));
+        self.start = Some((x, y));
+        self.current = Some((x, y));
+        self
+    }
+    pub fn line_to(mut self, x: f32, y: f32) -> Self {
+        if self.current.is_none() { return self.move_to(x, y) }
+        self.segments.push(Segment::LineTo((x, y)));
+        self.current = Some((x, y));
+        self
+    }
+    pub fn quad_to(mut self, cx: f32, cy: f32, x: f32, y: f32) -> Self {
+        if self.current.is_none() { self = self.move_to(cx, cy) }
+        self.segments.push(Segment::QuadTo((cx, cy), (x, y)));
+        self.current = Some((x, y));
+        self
+    }
+    pub fn cubic_to(mut self, c1x: f32, c1y: f32, c2x: f32, c2y: f32, x: f32, y: f32) -> Self {
+        if self.current.is_none() { self = self.move_to(c1x, c1y) }
+        self.segments.push(Segment::CubicTo((c1x, c1y), (c2x, c2y), (x, y)));
+        self.current = Some((x, y));
+        self
+    }
+    // Angles are in radians, clockwise from the positive x axis as on a canvas.
+    pub fn arc(mut self, cx: f32, cy: f32, radius: f32, start_angle: f32, end_angle: f32, counterclockwise: bool) -> Self {
+        let radius = radius.abs();
+        let mut sweep = end_angle - start_angle;
+        if counterclockwise {
+            if sweep > -2. * PI { sweep = -(-sweep).rem_euclid(2. * PI) } else { sweep = -2. * PI }
+        } else if sweep < 2. * PI {
+            sweep = sweep.rem_euclid(2. * PI)
+        } else {
+            sweep = 2. * PI
+        }
+        let point = |angle: f32| (cx + radius * angle.cos(), cy + radius * angle.sin());
+        self = self.line_to(point(start_angle).0, point(start_angle).1);
+        let count = (sweep.abs() / FRAC_PI_2).ceil().max(1.) as usize;
+        let step = sweep / count as f32;
+        let k = 4. / 3. * (step / 4.).tan() * radius;
+        for i in 0..count {
+            let a0 = start_angle + step * i as f32;
+            let a1 = a0 + step;
+            let (p0, p1) = (point(a0), point(a1));
+            self = self.cubic_to(
+                p0.0 - k * a0.sin(), p0.1 + k * a0.cos(),
+                p1.0 + k * a1.sin(), p1.1 - k * a1.cos(),
+                p1.0, p1.1
+            )
+        }
+        self
+    }
+    pub fn circle(self, cx: f32, cy: f32, radius: f32) -> Self {
+        self.move_to(cx + radius, cy).arc(cx, cy, radius, 0., 2. * PI, false).close()
+    }
+    pub fn rect(self, x: f32, y: f32, width: f32, height: f32) -> Self {
+        self.move_to(x, y).line_to(x + width, y).line_to(x + width, y + height).line_to(x, y + height).close()
+    }
+    pub fn close(mut self) -> Self {
+        if let Some(start) = self.start {
+            self.segments.push(Segment::Close);
+            self.current = Some(start)
+        }
+        self
+    }
+
+    pub(crate) fn bounds(&self) -> Rect {
+        self.flatten(0.1).iter().flat_map(|(points, _)| points).fold(None, |r: Option<Rect>, &(x, y)| Some(match r {
+            Some(r) => Rect::new(r.x0.min(x), r.y0.min(y), r.x1.max(x), r.y1.max(y)),
+            None => Rect::new(x, y, x, y)
+        })).unwrap_or_default()
+    }
+
+    // Splits the path into polylines, each flagged as closed or open.
+    pub(crate) fn flatten(&self, tolerance: f32) -> Vec<(Vec<Point>, bool)> {
+        let mut res = Vec::new();
+        let mut current: Vec<Point> = Vec::new();
+        let mut last = (0., 0.);
+        let subdivisions = |dd: f32| ((dd / tolerance).sqrt().ceil() as usize).clamp(1, 256);
+        for segment in &self.segments {
+            match *segment {
+                Segment::MoveTo(p) => {
+                    if current.len() > 1 { res.push((std::mem::take(&mut current), false)) }
+                    current = vec![p]
+                }
+                Segment::LineTo(p) => current.push(p),
+                Segment::QuadTo(c, p) => {
+                    let dd = length((last.0 - 2. * c.0 + p.0, last.1 - 2. * c.1 + p.1));
+                    let n = subdivisions(dd / 8.);
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let u = 1. - t;
+                        current.push((
+                            u * u * last.0 + 2. * u * t * c.0 + t * t * p.0,
+                            u * u * last.1 + 2. * u * t * c.1 + t * t * p.1
+                        ))
+                    }
+                }
+                Segment::CubicTo(c1, c2, p) => {
+                    let dd = length((last.0 - 2. * c1.0 + c2.0, last.1 - 2. * c1.1 + c2.1))
+                        .max(length((c1.0 - 2. * c2.0 + p.0, c1.1 - 2. * c2.1 + p.1)));
+                    let n = subdivisions(dd * 0.75);
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let u = 1. - t;
+                        let (a, b, c, d) = (u * u * u, 3. * u * u * t, 3. * u * t * t, t * t * t);
+                        current.push((
+                            a * last.0 + b * c1.0 + c * c2.0 + d * p.0,
+                            a * last.1 + b * c1.1 + c * c2.1 + d * p.1
+                        ))
+                    }
+                }
+                Segment::Close => {
+                    let start = current.first().copied().unwrap_or(last);
+                    if current.len() > 1 { res.push((std::mem::take(&mut current), true)) }
+                    current = vec![start]
+                }
+            }
+            last = current.last().copied().unwrap_or(last)
+        }
+        if current.len() > 1 { res.push((current, false)) }
+        for (points, _) in res.iter_mut() {
+            points.dedup_by(|a, b| length((a.0 - b.0, a.1 - b.1)) < 1e-6)
+        }
+        res
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum FillRule {
+    #[default]
+    NonZero,
+    EvenOdd
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum LineJoin {
+    #[default]
+    Miter,
+    Round,
+    Bevel
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum LineCap {
+    #[default]
+    Butt,
+    Round,
+    Square
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stroke {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f32,
+    pub dash: Vec<f32>,
+    pub dash_offset: f32
+}
+impl Default for Stroke {
+    fn default() -> Self {
+        Self { width: 1., join: LineJoin::Miter, cap: LineCap::Butt, miter_limit: 10., dash: Vec::new(), dash_offset: 0. }
+    }
+}
+impl Stroke {
+    pub fn new(width: f32) -> Self {
+        Self { width, ..Default::default() }
+    }
+    pub fn join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+    pub fn cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+    pub fn miter_limit(mut self, miter_limit: f32) -> Self {
+        self.miter_limit = miter_limit;
+        self
+    }
+    pub fn dash(mut self, dash: &[f32], offset: f32) -> Self {
+        self.dash = dash.to_vec();
+        self.dash_offset = offset;
+        self
+    }
+
+    // Outlines the polylines as a set of positively oriented polygons, so that a non-zero fill draws their union.
+    pub(crate) fn outline(&self, polylines: Vec<(Vec<Point>, bool)>, tolerance: f32) -> Vec<Vec<Point>> {
+        let hw = self.width / 2.;
+        let mut res = Vec::new();
+        if hw.is_nan() || hw <= 0. { return res }
+        for (points, closed) in self.apply_dash(polylines) {
+            let count = points.len();
+            if count < 2 { continue }
+            let segments = if closed { count } else { count - 1 };
+            let dir = |i: usize| {
+                let (a, b) = (points[i % count], points[(i + 1) % count]);
+                normalize((b.0 - a.0, b.1 - a.1))
+            };
+            for i in 0..segments {
+                let (a, b) = (points[i], points[(i + 1) % count]);
+                let d = dir(i);
+                let n = (-d.1 * hw, d.0 * hw);
+                res.push(vec![(a.0 + n.0, a.1 + n.1), (b.0 + n.0, b.1 + n.1), (b.0 - n.0, b.1 - n.1), (a.0 - n.0, a.1 - n.1)])
+            }
+            let joins = if closed { 0..count } else { 1..count - 1 };
+            for i in joins {
+                let d0 = dir((i + count - 1) % count);
+                self.join_polygon(points[i], d0, dir(i), hw, tolerance, &mut res)
+            }
+            if !closed {
+                let d = dir(0);
+                self.cap_polygon(points[0], (-d.0, -d.1), hw, tolerance, &mut res);
+                self.cap_polygon(points[count - 1], dir(count - 2), hw, tolerance, &mut res)
+            }
+        }
+        for polygon in res.iter_mut() {
+            if signed_area(polygon) < 0. { polygon.reverse() }
+        }
+        res
+    }
+
+    fn join_polygon(&self, v: Point, d0: Point, d1: Point, hw: f32, tolerance: f32, res: &mut Vec<Vec<Point>>) {
+        let cross = d0.0 * d1.1 - d0.1 * d1.0;
+        let dot = d0.0 * d1.0 + d0.1 * d1.1;
+        if cross.abs() < 1e-6 && dot > 0. { return }
+        // The outer side of the turn is the one facing away from the outgoing direction.
+        let n0 = (-d0.1, d0.0);
+        let side = if n0.0 * d1.0 + n0.1 * d1.1 > 0. { -1. } else { 1. };
+        let o0 = (-d0.1 * side * hw, d0.0 * side * hw);
+        let o1 = (-d1.1 * side * hw, d1.0 * side * hw);
+        let (p0, p1) = ((v.0 + o0.0, v.1 + o0.1), (v.0 + o1.0, v.1 + o1.1));
+        match self.join {
+            LineJoin::Round => res.push(arc_polygon(v, o0, (d0.0 - d1.0, d0.1 - d1.1), hw, tolerance)),
+            LineJoin::Miter if dot > -1. + 1e-6 => {
+                let m = normalize((o0.0 + o1.0, o0.1 + o1.1));
+                let cos = (m.0 * o0.0 + m.1 * o0.1) / hw;
+                if cos > 1e-6 && 1. / cos <= self.miter_limit {
+                    res.push(vec![v, p0, (v.0 + m.0 * hw / cos, v.1 + m.1 * hw / cos), p1])
+                } else {
+                    res.push(vec![v, p0, p1])
+                }
+            }
+            _ => res.push(vec![v, p0, p1])
+        }
+    }
+
+    fn cap_polygon(&self, v: Point, d: Point, hw: f32, tolerance: f32, res: &mut Vec<Vec<Point>>) {
+        let n = (-d.1 * hw, d.0 * hw);
+        match self.cap {
+            LineCap::Butt => {}
+            LineCap::Square => {
+                let e = (v.0 + d.0 * hw, v.1 + d.1 * hw);
+                res.push(vec![(v.0 + n.0, v.1 + n.1), (e.0 + n.0, e.1 + n.1), (e.0 - n.0, e.1 - n.1), (v.0 - n.0, v.1 - n.1)])
+            }
+            LineCap::Round => res.push(arc_polygon(v, n, d, hw, tolerance))
+        }
+    }
+
+    fn apply_dash(&self, polylines: Vec<(Vec<Point>, bool)>) -> Vec<(Vec<Point>, bool)> {
+        let total: f32 = self.dash.iter().sum();
+        if self.dash.is_empty() || self.dash.iter().any(|v| !(v.is_finite() && *v >= 0.)) || total <= 0. { return polylines }
+        let pattern = if self.dash.len() % 2 == 1 { [self.dash.clone(), self.dash.clone()].concat() } else { self.dash.clone() };
+        let total = total * (pattern.len() / self.dash.len()) as f32;
+        let mut res = Vec::new();
+        for (mut points, closed) in polylines {
+            if closed { points.push(points[0]) }
+            let mut i = 0;
+            let mut offset = self.dash_offset.rem_euclid(total);
+            while offset >= pattern[i] {
+                offset -= pattern[i];
+                i = (i + 1) % pattern.len()
+            }
+            let mut remaining = pattern[i] - offset;
+            let mut current = if i % 2 == 0 { vec![points[0]] } else { Vec::new() };
+            for w in points.windows(2) {
+                let (a, b) = (w[0], w[1]);
+                let length = length((b.0 - a.0, b.1 - a.1));
+                let mut travelled = 0.;
+                while length - travelled > remaining {
+                    travelled += remaining;
+                    let t = travelled / length;
+                    let p = (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+                    if i % 2 == 0 {
+                        current.push(p);
+                        res.push((std::mem::take(&mut current), false))
+                    } else {
+                        current = vec![p]
+                    }
+                    i = (i + 1) % pattern.len();
+                    remaining = pattern[i]
+                }
+                remaining -= length - travelled;
+                if i % 2 == 0 { current.push(b) }
+            }
+            if current.len() > 1 { res.push((current, false)) }
+        }
+        res
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum SpreadMethod {
+    #[default]
+    Pad,
+    Reflect,
+    Repeat
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GradientKind {
+    Linear { x1: f32, y1: f32, x2: f32, y2: f32 },
+    Radial { cx: f32, cy: f32, r: f32, fx: f32, fy: f32 }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub stops: Vec<(f32, Color)>,
+    pub spread: SpreadMethod,
+    pub transform: Matrix
+}
+impl Gradient {
+    pub fn linear(x1: f32, y1: f32, x2: f32, y2: f32) -> Self {
+        Self { kind: GradientKind::Linear { x1, y1, x2, y2 }, stops: Vec::new(), spread: SpreadMethod::Pad, transform: Matrix::IDENTITY }
+    }
+    pub fn radial(cx: f32, cy: f32, r: f32) -> Self {
+        Self { kind: GradientKind::Radial { cx, cy, r, fx: cx, fy: cy }, stops: Vec::new(), spread: SpreadMethod::Pad, transform: Matrix::IDENTITY }
+    }
+    pub fn stop(mut self, offset: f32, color: Color) -> Self {
+        let last = self.stops.last().map_or(0., |s| s.0);
+        self.stops.push((offset.clamp(last, 1.), color));
+        self
+    }
+    pub fn spread(mut self, spread: SpreadMethod) -> Self {
+        self.spread = spread;
+        self
+    }
+    pub fn transform(mut self, transform: Matrix) -> Self {
+        self.transform = transform;
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Paint {
+    Solid(Color),
+    Gradient(Gradient)
+}
+impl Paint {
+    pub fn is_visible(&self) -> bool {
+        match self {
+            Self::Solid(c) => c.a > 0.,
+            Self::Gradient(g) => g.stops.iter().any(|(_, c)| c.a > 0.)
+        }
+    }
+    pub fn with_opacity(self, opacity: f32) -> Self {
+        match self {
+            Self::Solid(c) => Self::Solid(Color { a: c.a * opacity, ..c }),
+            Self::Gradient(mut g) => {
+                for (_, c) in g.stops.iter_mut() { c.a *= opacity }
+                Self::Gradient(g)
+            }
+        }
+    }
+    pub(crate) fn color(&self) -> Color {
+        match self {
+            Self::Solid(c) => *c,
+            Self::Gradient(_) => Color::WHITE
+        }
+    }
+    // Gradients are laid out as a header, the window to gradient space matrix, the geometry and then offset and color pairs.
+    pub(crate) fn data(&self, matrix: &Matrix) -> Vec<[f32; 4]> {
+        let g = match self { Self::Gradient(g) if !g.stops.is_empty() => g, _ => return Vec::new() };
+        let inv = match matrix.multiply(&g.transform).inverse() { Some(v) => v, None => return Vec::new() };
+        let spread = match g.spread { SpreadMethod::Pad => 0., SpreadMethod::Reflect => 1., SpreadMethod::Repeat => 2. };
+        let (kind, r, geometry) = match g.kind {
+            GradientKind::Linear { x1, y1, x2, y2 } => (1., 0., [x1, y1, x2, y2]),
+            GradientKind::Radial { cx, cy, r, fx, fy } => {
+                // The focal point is kept inside the circle as SVG requires.
+                let (dx, dy) = (fx - cx, fy - cy);
+                let scale = (0.99 * r / length((dx, dy)).max(1e-6)).min(1.);
+                (2., r, [cx, cy, cx + dx * scale, cy + dy * scale])
+            }
+        };
+        let mut res = vec![[kind, spread, g.stops.len() as f32, 0.], [inv.a, inv.b, inv.c, inv.d], [inv.e, inv.f, r, 0.], geometry];
+        for (offset, c) in &g.stops {
+            res.push([*offset, 0., 0., 0.]);
+            res.push([c.r, c.g, c.b, c.a])
+        }
+        res
+    }
+}
+impl From<Color> for Paint {
+    fn from(color: Color) -> Self {
+        Self::Solid(color)
+    }
+}
+impl From<Gradient> for Paint {
+    fn from(gradient: Gradient) -> Self {
+        Self::Gradient(gradient)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum DrawStyle {
+    Fill(FillRule),
+    Stroke(Stroke)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct DrawOp {
+    pub path: Path,
+    pub paint: Paint,
+    pub style: DrawStyle,
+    pub transform: Matrix
+}
+impl DrawOp {
+    pub fn is_even_odd(&self) -> bool {
+        matches!(self.style, DrawStyle::Fill(FillRule::EvenOdd))
+    }
+
+    // Returns the outline as edges in window space, given the matrix mapping element space to the window.
+    pub fn edges(&self, matrix: &Matrix) -> Vec<[f32; 4]> {
+        let matrix = matrix.multiply(&self.transform);
+        let tolerance = 0.25 / (matrix.a * matrix.d - matrix.b * matrix.c).abs().sqrt().max(1e-3);
+        let polygons = match &self.style {
+            DrawStyle::Fill(_) => self.path.flatten(tolerance).into_iter().map(|(points, _)| points).collect(),
+            DrawStyle::Stroke(stroke) => stroke.outline(self.path.flatten(tolerance), tolerance)
+        };
+        let mut res = Vec::new();
+        for polygon in polygons.iter().filter(|p| p.len() > 1) {
+            for i in 0..polygon.len() {
+                let (x0, y0) = matrix.apply(polygon[i].0, polygon[i].1);
+                let (x1, y1) = matrix.apply(polygon[(i + 1) % polygon.len()].0, polygon[(i + 1) % polygon.len()].1);
+                res.push([x0, y0, x1, y1])
+            }
+        }
+        res
+    }
+    pub fn paint_data(&self, matrix: &Matrix) -> Vec<[f32; 4]> {
+        self.paint.data(&matrix.multiply(&self.transform))
+    }
+}
+
+// An element drawn with vector paths, in coordinates relative to its top left corner.
+#[derive(Clone, PartialEq)]
+pub struct Canvas(Element);
+impl Default for Canvas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Canvas {
+    pub fn new() -> Self {
+        let element = Element::default();
+        element.set_tag("canvas");
+        Self(element)
+    }
+    pub fn fill(&self, path: &Path, paint: impl Into<Paint>, rule: FillRule) {
+        self.push(path, paint.into(), DrawStyle::Fill(rule))
+    }
+    pub fn stroke(&self, path: &Path, paint: impl Into<Paint>, stroke: &Stroke) {
+        self.push(path, paint.into(), DrawStyle::Stroke(stroke.clone()))
+    }
+    pub fn clear(&self) {
+        let mut drawing = self.0.drawing();
+        if !drawing.is_empty() {
+            drawing.clear();
+            self.0.mark_changed()
+        }
+    }
+    fn push(&self, path: &Path, paint: Paint, style: DrawStyle) {
+        if paint.is_visible() && !path.is_empty() {
+            self.0.drawing().push(DrawOp { path: path.clone(), paint, style, transform: Matrix::IDENTITY });
+            self.0.mark_changed()
+        }
+    }
+}
+impl From<Element> for Canvas {
+    fn from(element: Element) -> Self {
+        Self(element)
+    }
+}
+impl Deref for Canvas {
+    type Target = Element;
+    fn deref(&self) -> &Element {
+        &self.0
+    }
+}
+
+fn length(v: Point) -> f32 {
+    (v.0 * v.0 + v.1 * v.1).sqrt()
+}
+fn normalize(v: Point) -> Point {
+    let l = length(v);
+    if l > 0. { (v.0 / l, v.1 / l) } else { (1., 0.) }
+}
+fn signed_area(polygon: &[Point]) -> f32 {
+    (0..polygon.len()).map(|i| {
+        let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        a.0 * b.1 - b.0 * a.1
+    }).sum::<f32>() / 2.
+}
+// A pie slice around v, from the offset `from` to its mirror image through `middle`.
+fn arc_polygon(v: Point, from: Point, middle: Point, radius: f32, tolerance: f32) -> Vec<Point> {
+    let start = from.1.atan2(from.0);
+    let mid = middle.1.atan2(middle.0);
+    let sweep = 2. * (mid - start + PI).rem_euclid(2. * PI) - 2. * PI;
+    let step = 2. * (1. - (tolerance / radius).min(1.)).acos().max(0.05);
+    let count = (sweep.abs() / step).ceil().clamp(1., 64.) as usize;
+    let mut res = vec![v];
+    for i in 0..=count {
+        let angle = start + sweep * i as f32 / count as f32;
+        res.push((v.0 + radius * angle.cos(), v.1 + radius * angle.sin()))
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(path: Path, style: DrawStyle) -> DrawOp {
+        DrawOp { path, paint: Color::BLACK.into(), style, transform: Matrix::IDENTITY }
+    }
+    fn bounds(edges: &[[f32; 4]]) -> Rect {
+        edges.iter().fold(Rect::new(f32::MAX, f32::MAX, f32::MIN, f32::MIN), |r, e| {
+            Rect::new(r.x0.min(e[0]).min(e[2]), r.y0.min(e[1]).min(e[3]), r.x1.max(e[0]).max(e[2]), r.y1.max(e[1]).max(e[3]))
+        })
+    }
+    fn winding(edges: &[[f32; 4]], x: f32, y: f32) -> i32 {
+        edges.iter().map(|&[x0, y0, x1, y1]| {
+            let side = (x1 - x0) * (y - y0) - (x - x0) * (y1 - y0);
+            match (y0 <= y && y < y1, y1 <= y && y < y0) {
+                (true, _) if side > 0. => 1,
+                (_, true) if side < 0. => -1,
+                _ => 0
+            }
+        }).sum()
+    }
+    fn covered(op: &DrawOp, x: f32, y: f32) -> bool {
+        let winding = winding(&op.edges(&Matrix::IDENTITY), x, y);
+        if op.is_even_odd() { winding % 2 != 0 } else { winding != 0 }
+    }
+
+    #[test]
+    fn dashes() {
+        let line = Path::new().move_to(0., 0.).line_to(100., 0.);
+        for (dash, offset, segments) in [(vec![10., 10.], 0., 5), (vec![10., 10.], 5., 6), (vec![10.], 0., 5), (vec![30., 10., 10., 10.], 0., 3), (vec![], 0., 1)] {
+            let edges = op(line.clone(), DrawStyle::Stroke(Stroke::new(2.).dash(&dash, offset))).edges(&Matrix::IDENTITY);
+            assert_eq!(edges.len(), segments * 4, "{dash:?} {offset}");
+        }
+    }
+
+    #[test]
+    fn caps() {
+        let line = Path::new().move_to(10., 10.).line_to(50., 10.);
+        for (cap, expected) in [(LineCap::Butt, Rect::new(10., 8., 50., 12.)), (LineCap::Square, Rect::new(8., 8., 52., 12.))] {
+            let edges = op(line.clone(), DrawStyle::Stroke(Stroke::new(4.).cap(cap))).edges(&Matrix::IDENTITY);
+            assert_eq!(bounds(&edges), expected, "{cap:?}");
+        }
+        let edges = op(line, DrawStyle::Stroke(Stroke::new(4.).cap(LineCap::Round))).edges(&Matrix::IDENTITY);
+        let r = bounds(&edges);
+        assert!((r.x0 - 8.).abs() < 0.01 && (r.x1 - 52.).abs() < 0.01, "{r:?}");
+    }
+
+    #[test]
+    fn fill_rules() {
+        // A pentagram winds twice around its center.
+        let mut star = Path::new();
+        for k in 0..5 {
+            let angle = (-90. + k as f32 * 144.).to_radians();
+            let (x, y) = (50. + 50. * angle.cos(), 50. + 50. * angle.sin());
+            star = if k == 0 { star.move_to(x, y) } else { star.line_to(x, y) }
+        }
+        let star = star.close();
+        let non_zero = op(star.clone(), DrawStyle::Fill(FillRule::NonZero));
+        let even_odd = op(star, DrawStyle::Fill(FillRule::EvenOdd));
+        assert_eq!(winding(&non_zero.edges(&Matrix::IDENTITY), 50., 50.).abs(), 2);
+        assert!(covered(&non_zero, 50., 50.));
+        assert!(!covered(&even_odd, 50., 50.));
+        assert!(covered(&non_zero, 50., 10.) && covered(&even_odd, 50., 10.));
+        assert!(!covered(&non_zero, 5., 5.) && !covered(&even_odd, 5., 5.));
+    }
+
+    #[test]
+    fn transform() {
+        let mut op = op(Path::new().rect(0., 0., 10., 10.), DrawStyle::Fill(FillRule::NonZero));
+        op.transform = Matrix::new(2., 0., 0., 2., 5., 0.);
+        assert_eq!(bounds(&op.edges(&Matrix::new(1., 0., 0., 1., 0., 100.))), Rect::new(5., 100., 25., 120.));
+    }
+}
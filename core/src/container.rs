@@ -1,110 +1,219 @@
-use std::sync::{Arc, Mutex};
-use wgpu::{util::DeviceExt, CommandEncoder, TextureView};
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, Ordering}};
+use wgpu::{util::DeviceExt, CommandEncoder};
 use winit::dpi::PhysicalSize;
 
-use crate::{Context, Script, Element};
+use crate::{
+    Context, Script, Scripts, SharedScript, ScriptHandle, with_script, Element, Rect, WindowSize, RenderTargets, render_pass, BACKDROP, TEMP,
+    css::{self, Stylesheet}, shader::{RECORD_SIZE, DisplayList, RenderCommand}
+};
 
 #[derive(Clone, Default)]
 pub struct Container {
-    scripts: Arc<Mutex<Vec<Arc<Box<dyn Script>>>>>,
+    scripts: Scripts,
     pub root: Element,
+    stylesheets: Arc<Mutex<Vec<Stylesheet>>>,
+    data_version: Arc<AtomicU64>,
+    display_list: Arc<Mutex<DisplayList>>,
+    damage: Arc<Mutex<Rect>>,
+    animating: Arc<AtomicBool>,
     buffer: Arc<Mutex<Option<wgpu::Buffer>>>,
+    path_buffer: Arc<Mutex<Option<wgpu::Buffer>>>,
     bind_group: Arc<Mutex<Option<wgpu::BindGroup>>>
 }
 impl Container {
-    pub fn add_script(&self, script: impl Script + 'static) -> &Self {
-        self.scripts.lock().unwrap().push(Arc::new(Box::new(script)));
+    // Scripts added after startup are set up on the next frame.
+    pub fn add_script(&self, script: impl Script + 'static) -> ScriptHandle {
+        self.add_script_with_priority(script, 0)
+    }
+    pub fn add_script_with_priority(&self, script: impl Script + 'static, priority: i32) -> ScriptHandle {
+        self.scripts.add(script, priority)
+    }
+    pub fn get_scripts(&self) -> Vec<ScriptHandle> {
+        self.scripts.handles()
+    }
+    pub(crate) fn scripts(&self) -> Vec<SharedScript> {
+        self.scripts.enabled()
+    }
+
+    pub fn add_stylesheet(&self, stylesheet: Stylesheet) -> &Self {
+        self.stylesheets.lock().unwrap().push(stylesheet);
+        self.root.mark_style_dirty();
         self
     }
     
+    pub fn query_selector(&self, selector: &str) -> Option<Element> {
+        if self.root.matches(selector) { return Some(self.root.clone()) }
+        self.root.query_selector(selector)
+    }
+    pub fn query_selector_all(&self, selector: &str) -> Vec<Element> {
+        let mut res = self.root.query_selector_all(selector);
+        if self.root.matches(selector) { res.insert(0, self.root.clone()) }
+        res
+    }
+    pub fn get_element_by_id(&self, id: &str) -> Option<Element> {
+        if self.root.get_id().as_deref() == Some(id) { return Some(self.root.clone()) }
+        self.root.get_element_by_id(id)
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.animating.load(Ordering::Relaxed)
+    }
+    pub(crate) fn version(&self) -> u64 {
+        self.root.get_version()
+    }
+    pub(crate) fn scripts_version(&self) -> u64 {
+        self.scripts.changes.version()
+    }
+
     pub fn setup(&self, c: &Context) {
         self.root.style.width.set_perc(1.);
         self.root.style.height.set_perc(1.);
-        for script in self.scripts.lock().unwrap().iter() {
-            script.setup(c.clone())
+        self.setup_scripts(c)
+    }
+    pub(crate) fn setup_scripts(&self, c: &Context) {
+        for script in self.scripts.take_pending_setup() {
+            with_script(&script, |script| script.setup(c.clone()));
         }
     }
 
     pub fn resize(&self, c: &Context, new_size: PhysicalSize<u32>) {
-        for script in self.scripts.lock().unwrap().iter() {
-            script.resize(c.clone(), new_size)
+        for script in self.scripts() {
+            with_script(&script, |script| script.resize(c.clone(), new_size));
+        }
+    }
+
+    pub fn fixed_update(&self, c: &Context) {
+        for script in self.scripts() {
+            with_script(&script, |script| script.fixed_update(c.clone()));
         }
     }
 
     pub fn update(&self, c: &Context) {
-        let scripts = self.scripts.lock().unwrap().clone();
-        for script in scripts {
-            script.update(c.clone());
+        for script in self.scripts() {
+            with_script(&script, |script| script.update(c.clone()));
         }
-        
-        let window_size = c.window.inner_size().into();
-        let mut data = self.root.get_data(window_size);
 
-        if data.len() == 0 {
-            data.append(&mut vec![0.;4]);
+        if self.root.has_style_dirty() {
+            css::apply_dirty(&self.root, &self.stylesheets.lock().unwrap());
+            self.root.load_pending_images(c);
         }
-        
-        let data: &[u8] = bytemuck::cast_slice(&data);
+        self.animating.store(self.root.tick_animations(c.get_elapsed()), Ordering::Relaxed);
 
+        let version = self.version() + c.window_version();
+        if self.data_version.swap(version, Ordering::Relaxed) == version && self.bind_group.lock().unwrap().is_some() {
+            return
+        }
+        
+        let window_size: WindowSize = c.window.inner_size().into();
+        let display_list = self.root.get_display_list(window_size);
         let mut buffer = self.buffer.lock().unwrap();
+        let mut path_buffer = self.path_buffer.lock().unwrap();
         let mut bind_group = self.bind_group.lock().unwrap();
+        let full = Rect::new(0., 0., window_size.width, window_size.height);
 
-        if data.len() == 0 {
-            *buffer = None;
-            *bind_group = None;
-        } else {
-            match buffer.as_mut() {
-                Some(buffer) => {
-                    if buffer.size() >= data.len() as u64 {
-                        c.queue.write_buffer(buffer, 0, data);
-                    } else {
-                        *buffer = c.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: None,
-                            contents: data,
-                            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
-                        });
-                        *bind_group = None
-                    }
+        let mut old = self.display_list.lock().unwrap();
+        let paths_recreated = sync_buffer(c, &mut path_buffer, &old.path_data, &display_list.path_data, 4);
+        let recreated = sync_buffer(c, &mut buffer, &old.data, &display_list.data, RECORD_SIZE);
+        let mut damage = if recreated { full } else { display_list.damage(&old) };
+        let has_effects = display_list.has_effects();
+        *old = display_list;
+
+        if recreated || paths_recreated || bind_group.is_none() {
+            *bind_group = Some(c.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &c.pipelines().data_layout,
+                entries: &[
+                    wgpu::BindGroupEntry { binding: 0, resource: buffer.as_ref().unwrap().as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 1, resource: path_buffer.as_ref().unwrap().as_entire_binding() }
+                ]
+            }))
+        }
+        // Blurs read pixels outside of the damaged area, so containers using them are redrawn entirely.
+        if has_effects && !damage.is_empty() {
+            damage = full
+        }
+        let mut current = self.damage.lock().unwrap();
+        *current = current.union(&damage)
+    }
+    pub(crate) fn take_damage(&self) -> Rect {
+        std::mem::take(&mut self.damage.lock().unwrap())
+    }
+
+    pub(crate) fn render(&self, c: &Context, encoder: &mut CommandEncoder, targets: &mut RenderTargets, clip: Option<Rect>) {
+        let bind_group = self.bind_group.lock().unwrap();
+        let bind_group = match bind_group.as_ref() { Some(v) => v, None => return };
+        let clip = clip.map(|clip| clip.intersect(&self.root.get_rect(c.window.inner_size().into())));
+        if clip.is_some_and(|clip| clip.is_empty()) { return }
+
+        let commands = self.display_list.lock().unwrap().commands.clone();
+        let mut depth = 0;
+        for command in commands.iter() {
+            match command {
+                RenderCommand::PushLayer => depth += 1,
+                RenderCommand::PopLayer { .. } => depth -= 1,
+                _ => {}
+            }
+            targets.reserve(&c.device, &c.pipelines().texture_layout, depth)
+        }
+
+        let pipelines = c.pipelines_for(targets.format);
+        let blur_params = |blur: f32, direction: u32| ((blur * 16.).round() as u32) << 1 | direction;
+        let mut depth = 0;
+        for command in commands {
+            let target = targets.get(RenderTargets::layer(depth));
+            match command {
+                RenderCommand::Draw(start) => render_pass(
+                    encoder, &target.view, false, &pipelines.main,
+                    &[bind_group, &targets.get(BACKDROP).bind_group], start, clip
+                ),
+                RenderCommand::PushLayer => {
+                    depth += 1;
+                    render_pass(encoder, &targets.get(RenderTargets::layer(depth)).view, true, &pipelines.clear, &[], 0, clip)
                 }
-                None => {
-                    *buffer = Some(c.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: None,
-                        contents: data,
-                        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
-                    }));
-                    *bind_group = None
+                RenderCommand::PopLayer { blur } => {
+                    depth -= 1;
+                    render_pass(encoder, &targets.get(TEMP).view, true, &pipelines.blur, &[&target.bind_group], blur_params(blur, 0), clip);
+                    let parent = targets.get(RenderTargets::layer(depth));
+                    render_pass(encoder, &parent.view, false, &pipelines.blur_blend, &[&targets.get(TEMP).bind_group], blur_params(blur, 1), clip)
+                }
+                RenderCommand::Backdrop { blur } => {
+                    render_pass(encoder, &targets.get(TEMP).view, true, &pipelines.blur, &[&target.bind_group], blur_params(blur, 0), clip);
+                    render_pass(encoder, &targets.get(BACKDROP).view, true, &pipelines.blur, &[&targets.get(TEMP).bind_group], blur_params(blur, 1), clip)
                 }
-            }
-            if bind_group.is_none() {
-                *bind_group = Some(c.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: None,
-                    layout: &c.shader.get_bind_group_layout(0),
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: buffer.as_ref().unwrap().as_entire_binding()
-                    }]
-                }))
             }
         }
     }
-    pub fn render(&self, c: &Context, encoder: &mut CommandEncoder, view: &TextureView, set_scissor_rect: bool) {
-        let bind_group = self.bind_group.lock().unwrap();
-        let bind_group = match bind_group.as_ref() { Some(v) => v, None => return };
-        
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+}
+
+// Writes the runs of strides of `data` that differ from `old` into the buffer, recreating it with a power of two size when it is too small.
+// Returns whether the buffer was recreated.
+fn sync_buffer(c: &Context, buffer: &mut Option<wgpu::Buffer>, old: &[f32], data: &[f32], stride: usize) -> bool {
+    if buffer.as_ref().map_or(true, |b| b.size() < (data.len() * 4) as u64) {
+        let mut contents = data.to_vec();
+        contents.resize((data.len() / stride).max(1).next_power_of_two() * stride, 0.);
+        *buffer = Some(c.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view,
-                resolve_target: None,
-                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: true }
-            })],
-            depth_stencil_attachment: None
-        });
-        if set_scissor_rect {
-            self.root.set_scissor_rect(&mut render_pass, c.window.inner_size().into());
+            contents: bytemuck::cast_slice(&contents),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
+        }));
+        return true
+    }
+    let buffer = buffer.as_ref().unwrap();
+    // Strides past the end of `data` are cleared.
+    let write = |(start, end): (usize, usize)| {
+        let mut contents = data.get(start * stride..(end * stride).min(data.len())).unwrap_or_default().to_vec();
+        contents.resize((end - start) * stride, 0.);
+        c.queue.write_buffer(buffer, (start * stride * 4) as u64, bytemuck::cast_slice(&contents))
+    };
+    let mut changed: Option<(usize, usize)> = None;
+    for i in 0..old.len().max(data.len()) / stride {
+        let range = i * stride..(i + 1) * stride;
+        if old.get(range.clone()) != data.get(range) {
+            changed = Some(changed.map_or((i, i + 1), |(start, _)| (start, i + 1)))
+        } else if let Some(run) = changed.take() {
+            write(run)
         }
-        render_pass.set_pipeline(&c.shader);
-        render_pass.set_bind_group(0, bind_group, &[]);
-        render_pass.draw(0..6, 0..1);
     }
-}
\ No newline at end of file
+    if let Some(run) = changed { write(run) }
+    false
+}
@@ -1,58 +1,343 @@
-use std::{sync::{Arc, Mutex, atomic::AtomicBool}, collections::HashSet};
-use wgpu::{Surface, Device, Queue, SurfaceConfiguration, Instance, RenderPipeline};
-use winit::{window::{Window, WindowBuilder}, event_loop::EventLoop, dpi::PhysicalSize};
+use std::{sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, Ordering}}, collections::{HashSet, HashMap}, time::{Duration, Instant}, path::PathBuf, any::{Any, TypeId}};
+use wgpu::{Surface, Adapter, Device, Queue, SurfaceConfiguration, Instance};
+use winit::{window::Window, event_loop::{ControlFlow, EventLoopProxy}, dpi::PhysicalSize};
 
-use crate::{Key, Container, utils, shader};
+use crate::{AppConfig, AppEvent, EventSender, WindowOptions, Executor, TaskHandle, Error, Key, Container, Script, with_script, Element, ElementEvent, EventType, MouseButton, Clock, FrameTimer, Rect, RenderTargets, render_pass, CANVAS, utils::{self, Changes}, shader::{self, Pipelines}};
 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RedrawMode {
+    Continuous,
+    // Redraws only on requested frames, changes and animations, `Script::fixed_update` does not keep it running.
+    #[default]
+    OnDemand
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PresentMode {
+    #[default]
+    Vsync,
+    Immediate,
+    Mailbox
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ColorSpace {
+    #[default]
+    Srgb,
+    Linear
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    #[default]
+    Opaque,
+    PreMultiplied,
+    PostMultiplied
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SurfaceSettings {
+    pub present_mode: PresentMode,
+    pub color_space: ColorSpace,
+    pub alpha_mode: AlphaMode
+}
+impl SurfaceSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+    pub fn color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+    pub fn alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+}
+
+type WindowSetup = Box<dyn FnOnce(Result<&Context, Error>) + Send>;
+type Resources = Arc<Mutex<HashMap<TypeId, Arc<Mutex<Box<dyn Any + Send>>>>>>;
+
+// Each window has its own context, sharing the device, queue and pipelines with the others.
 #[derive(Clone)]
 pub struct Context {
     pub window: Arc<Window>,
     surface: Arc<Surface>,
+    instance: Arc<Instance>,
+    adapter: Arc<Adapter>,
     pub(crate) device: Arc<Device>,
     pub(crate) queue: Arc<Queue>,
     surface_config: Arc<Mutex<SurfaceConfiguration>>,
+    surface_settings: Arc<Mutex<SurfaceSettings>>,
     exit: Arc<AtomicBool>,
+    closed: Arc<AtomicBool>,
+    pending_windows: Arc<Mutex<Vec<(WindowOptions, WindowSetup)>>>,
     keys_pressed: Arc<Mutex<HashSet<Key>>>,
+    cursor_position: Arc<Mutex<(f32, f32)>>,
+    mouse_pressed: Arc<Mutex<Option<(MouseButton, Element)>>>,
+    timer: Arc<Mutex<FrameTimer>>,
+    redraw_mode: Arc<Mutex<RedrawMode>>,
+    frame_requested: Arc<AtomicBool>,
+    frame_deadline: Arc<Mutex<Option<Instant>>>,
+    rendered_version: Arc<AtomicU64>,
+    changes: Changes,
     pub(crate) containers: Arc<Mutex<Vec<Container>>>,
-    pub(crate) shader: Arc<RenderPipeline>
+    pipelines: Arc<Mutex<Vec<Arc<Pipelines>>>>,
+    resources: Resources,
+    executor: Executor,
+    event_sender: EventSender,
+    targets: Arc<Mutex<Option<RenderTargets>>>
 }
 impl Context {
-    pub(crate) fn new(event_loop: &EventLoop<()>) -> Self {
-        let window = WindowBuilder::new().build(event_loop).unwrap();
-        let instance = Instance::new(Default::default());
-        let surface = unsafe { instance.create_surface(&window) }.unwrap();
-        let adapter = utils::create_adapter(&instance, &surface);
-        let (device, queue) = utils::create_device_queue(&adapter);
-        let surface_config = utils::configure_surface(&window, &device, &adapter, &surface);
-        let shader = shader::new(&device, surface_config.format);
-        
-        Self {
+    pub(crate) fn try_new(window: Window, config: &AppConfig, proxy: EventLoopProxy<AppEvent>) -> Result<Self, Error> {
+        let instance = Instance::new(wgpu::InstanceDescriptor { backends: config.backends, ..Default::default() });
+        let surface = unsafe { instance.create_surface(&window) }?;
+        let adapter = utils::create_adapter(&instance, &surface, config)?;
+        let (device, queue) = utils::create_device_queue(&adapter, config)?;
+        let surface_config = utils::configure_surface(&window, &device, &adapter, &surface, &config.surface)?;
+        let pipelines = shader::new(&device, surface_config.format);
+        let wake = Mutex::new(proxy.clone());
+
+        Ok(Self {
             window: window.into(),
             surface: surface.into(),
+            instance: instance.into(),
+            adapter: adapter.into(),
             device: device.into(),
             queue: queue.into(),
             surface_config: Arc::new(Mutex::new(surface_config)),
+            surface_settings: Arc::new(Mutex::new(config.surface)),
             exit: AtomicBool::new(false).into(),
+            closed: Default::default(),
+            pending_windows: Default::default(),
             keys_pressed: Default::default(),
+            cursor_position: Default::default(),
+            mouse_pressed: Default::default(),
+            timer: Default::default(),
+            redraw_mode: Default::default(),
+            frame_requested: AtomicBool::new(true).into(),
+            frame_deadline: Default::default(),
+            rendered_version: Default::default(),
+            changes: Default::default(),
             containers: Default::default(),
-            shader: shader.into()
+            pipelines: Arc::new(Mutex::new(vec![pipelines.into()])),
+            resources: Default::default(),
+            executor: Executor::new(move || { wake.lock().unwrap().send_event(AppEvent::Wake).ok(); }),
+            event_sender: EventSender::new(proxy),
+            targets: Default::default()
+        })
+    }
+    pub(crate) fn try_open(&self, window: Window, settings: &SurfaceSettings) -> Result<Self, Error> {
+        let surface = unsafe { self.instance.create_surface(&window) }?;
+        if !self.adapter.is_surface_supported(&surface) { return Err(Error::IncompatibleSurface) }
+        let surface_config = utils::configure_surface(&window, &self.device, &self.adapter, &surface, settings)?;
+
+        Ok(Self {
+            window: window.into(),
+            surface: surface.into(),
+            instance: self.instance.clone(),
+            adapter: self.adapter.clone(),
+            device: self.device.clone(),
+            queue: self.queue.clone(),
+            surface_config: Arc::new(Mutex::new(surface_config)),
+            surface_settings: Arc::new(Mutex::new(*settings)),
+            exit: self.exit.clone(),
+            closed: Default::default(),
+            pending_windows: self.pending_windows.clone(),
+            keys_pressed: Default::default(),
+            cursor_position: Default::default(),
+            mouse_pressed: Default::default(),
+            timer: Default::default(),
+            redraw_mode: Default::default(),
+            frame_requested: AtomicBool::new(true).into(),
+            frame_deadline: Default::default(),
+            rendered_version: Default::default(),
+            changes: Default::default(),
+            containers: Default::default(),
+            pipelines: self.pipelines.clone(),
+            resources: self.resources.clone(),
+            executor: self.executor.clone(),
+            event_sender: self.event_sender.clone(),
+            targets: Default::default()
+        })
+    }
+
+    // The window is created by the event loop, `setup` receives its context to add containers, or the error
+    // if the window or its surface could not be created.
+    pub fn open_window(&self, options: WindowOptions, setup: impl FnOnce(Result<&Context, Error>) + Send + 'static) {
+        self.pending_windows.lock().unwrap().push((options, Box::new(setup)))
+    }
+    pub(crate) fn take_pending_windows(&self) -> Vec<(WindowOptions, WindowSetup)> {
+        std::mem::take(&mut self.pending_windows.lock().unwrap())
+    }
+    pub fn close_window(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.window.set_visible(false)
+    }
+    pub fn is_window_open(&self) -> bool {
+        !self.closed.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn surface_format(&self) -> wgpu::TextureFormat {
+        self.surface_config.lock().unwrap().format
+    }
+
+    pub fn get_adapter_info(&self) -> wgpu::AdapterInfo {
+        self.adapter.get_info()
+    }
+
+    // Windows may use different formats, so pipelines are cached for each one.
+    pub(crate) fn pipelines(&self) -> Arc<Pipelines> {
+        self.pipelines_for(self.surface_format())
+    }
+    pub(crate) fn pipelines_for(&self, format: wgpu::TextureFormat) -> Arc<Pipelines> {
+        let mut cache = self.pipelines.lock().unwrap();
+        if let Some(pipelines) = cache.iter().find(|v| v.format == format) {
+            return pipelines.clone()
         }
+        let pipelines: Arc<Pipelines> = cache[0].rebuild(&self.device, format).into();
+        cache.push(pipelines.clone());
+        pipelines
     }
-    
+
+    pub fn get_surface_settings(&self) -> SurfaceSettings {
+        *self.surface_settings.lock().unwrap()
+    }
+    pub fn set_surface_settings(&self, settings: SurfaceSettings) {
+        *self.surface_settings.lock().unwrap() = settings;
+        self.window.set_transparent(settings.alpha_mode != AlphaMode::Opaque);
+        match utils::configure_surface(&self.window, &self.device, &self.adapter, &self.surface, &settings) {
+            Ok(config) => *self.surface_config.lock().unwrap() = config,
+            Err(e) => return log::error!("{}", e)
+        }
+        self.changes.mark();
+        self.request_frame()
+    }
+
     pub(crate) fn request_redraw(&self) {
         self.window.request_redraw()
     }
 
-    pub(crate) fn key_pressed(&self, key: Key) {
-        self.keys_pressed.lock().unwrap().insert(key);
+    pub fn get_redraw_mode(&self) -> RedrawMode {
+        *self.redraw_mode.lock().unwrap()
+    }
+    pub fn set_redraw_mode(&self, mode: RedrawMode) {
+        *self.redraw_mode.lock().unwrap() = mode;
+        self.request_frame()
+    }
+    pub fn request_frame(&self) {
+        self.frame_requested.store(true, Ordering::Relaxed)
+    }
+    pub fn request_frame_in(&self, delay: Duration) {
+        let at = Instant::now() + delay;
+        let mut deadline = self.frame_deadline.lock().unwrap();
+        if deadline.map_or(true, |v| at < v) { *deadline = Some(at) }
+    }
+    pub fn is_animating(&self) -> bool {
+        self.containers.lock().unwrap().iter().any(|c| c.is_animating())
     }
-    pub(crate) fn key_released(&self, key: &Key) {
-        self.keys_pressed.lock().unwrap().remove(key);
+    // Changes that affect every container of the window, like a resize or new surface settings.
+    pub(crate) fn window_version(&self) -> u64 {
+        self.changes.version()
+    }
+    fn version(&self) -> u64 {
+        self.window_version() + self.containers.lock().unwrap().iter().map(|c| c.version() + c.scripts_version()).sum::<u64>()
+    }
+    pub(crate) fn needs_redraw(&self) -> bool {
+        self.get_redraw_mode() == RedrawMode::Continuous
+            || self.frame_requested.load(Ordering::Relaxed)
+            || self.rendered_version.load(Ordering::Relaxed) != self.version()
+            || self.is_animating()
+            || self.frame_deadline.lock().unwrap().is_some_and(|v| v <= Instant::now())
+    }
+    pub(crate) fn control_flow(&self) -> ControlFlow {
+        if !self.is_running() { return ControlFlow::Exit }
+        if self.needs_redraw() { return ControlFlow::Poll }
+        match *self.frame_deadline.lock().unwrap() {
+            Some(at) => ControlFlow::WaitUntil(at),
+            None => ControlFlow::Wait
+        }
+    }
+
+    // Input handlers return whether the event changed something a frame should be drawn for.
+    pub(crate) fn key_pressed(&self, key: Key) -> bool {
+        self.keys_pressed.lock().unwrap().insert(key)
+    }
+    pub(crate) fn key_released(&self, key: &Key) -> bool {
+        self.keys_pressed.lock().unwrap().remove(key)
     }
     pub fn is_key_pressed(&self, key: &Key) -> bool {
         self.keys_pressed.lock().unwrap().contains(key)
     }
     
+    pub fn get_cursor_position(&self) -> (f32, f32) {
+        *self.cursor_position.lock().unwrap()
+    }
+    pub fn hit_test(&self, x: f32, y: f32) -> Option<Element> {
+        let ws = self.window.inner_size().into();
+        let containers = self.containers.lock().unwrap().clone();
+        containers.iter().rev().find_map(|container| container.root.hit_test(ws, x, y))
+    }
+    pub(crate) fn cursor_moved(&self, x: f32, y: f32) -> bool {
+        *self.cursor_position.lock().unwrap() = (x, y);
+        match self.hit_test(x, y) {
+            Some(target) => ElementEvent::new(EventType::MouseMove, target, (x, y), None).dispatch(self),
+            None => false
+        }
+    }
+    pub(crate) fn mouse_pressed(&self, button: MouseButton) -> bool {
+        let position = self.get_cursor_position();
+        let target = match self.hit_test(position.0, position.1) { Some(v) => v, None => return false };
+        *self.mouse_pressed.lock().unwrap() = Some((button, target.clone()));
+        ElementEvent::new(EventType::MouseDown, target, position, Some(button)).dispatch(self)
+    }
+    pub(crate) fn mouse_released(&self, button: MouseButton) -> bool {
+        let position = self.get_cursor_position();
+        let pressed = self.mouse_pressed.lock().unwrap().take();
+        let target = match self.hit_test(position.0, position.1) { Some(v) => v, None => return false };
+        let called = ElementEvent::new(EventType::MouseUp, target.clone(), position, Some(button)).dispatch(self);
+        let mut common = match pressed {
+            Some((pressed_button, pressed_target)) if pressed_button == button => Some(pressed_target),
+            _ => return called
+        };
+        while let Some(e) = common {
+            if e.contains(&target) {
+                return ElementEvent::new(EventType::Click, e, position, Some(button)).dispatch(self) || called
+            }
+            common = e.get_parent()
+        }
+        called
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.timer.lock().unwrap().elapsed
+    }
+    pub fn get_delta(&self) -> Duration {
+        self.timer.lock().unwrap().delta
+    }
+    pub fn get_frame(&self) -> u64 {
+        self.timer.lock().unwrap().frame
+    }
+    pub fn get_fps(&self) -> f32 {
+        self.timer.lock().unwrap().fps
+    }
+    pub fn get_fixed_timestep(&self) -> Duration {
+        self.timer.lock().unwrap().fixed_timestep
+    }
+    pub fn set_fixed_timestep(&self, timestep: Duration) {
+        self.timer.lock().unwrap().fixed_timestep = timestep
+    }
+    pub fn get_fixed_alpha(&self) -> f32 {
+        self.timer.lock().unwrap().fixed_alpha()
+    }
+    pub fn set_clock(&self, clock: impl Clock + 'static) {
+        self.timer.lock().unwrap().set_clock(Box::new(clock))
+    }
+
     pub fn exit(&self) {
         self.exit.store(true, std::sync::atomic::Ordering::Relaxed)
     }
@@ -60,9 +345,46 @@ impl Context {
         !self.exit.load(std::sync::atomic::Ordering::Relaxed)
     }
 
+    // Futures run on the event loop thread between frames, so they can use the context and elements.
+    pub fn spawn<T: 'static>(&self, future: impl std::future::Future<Output = T> + 'static) -> TaskHandle<T> {
+        TaskHandle::spawn(&self.executor, future)
+    }
+    // Runs `f` on its own thread, the returned future resolves to `None` if it panicked.
+    pub fn spawn_blocking<T: Send + 'static>(&self, f: impl FnOnce() -> T + Send + 'static) -> impl std::future::Future<Output = Option<T>> {
+        crate::task::spawn_blocking(f)
+    }
+    pub fn event_sender(&self) -> EventSender {
+        self.event_sender.clone()
+    }
+    pub(crate) fn run_tasks(&self) -> bool {
+        self.executor.run()
+    }
+
+    // Resources are shared by every script and window, one value per type.
+    pub fn insert_resource<T: Any + Send>(&self, value: T) {
+        self.resources.lock().unwrap().insert(TypeId::of::<T>(), Arc::new(Mutex::new(Box::new(value))));
+    }
+    pub fn remove_resource<T: Any + Send>(&self) -> Option<T> {
+        let resource = self.resources.lock().unwrap().remove(&TypeId::of::<T>())?;
+        let value = std::mem::replace(&mut *resource.lock().unwrap(), Box::new(()));
+        value.downcast().ok().map(|v| *v)
+    }
+    pub fn has_resource<T: Any + Send>(&self) -> bool {
+        self.resources.lock().unwrap().contains_key(&TypeId::of::<T>())
+    }
+    pub fn get_resource<T: Any + Send + Clone>(&self) -> Option<T> {
+        self.with_resource(|v: &mut T| v.clone())
+    }
+    pub fn with_resource<T: Any + Send, R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let resource = self.resources.lock().unwrap().get(&TypeId::of::<T>())?.clone();
+        let mut resource = resource.lock().unwrap();
+        resource.downcast_mut().map(f)
+    }
+
     pub fn new_container(&self) -> Container {
         let container = Container::default();
         self.containers.lock().unwrap().push(container.clone());
+        self.changes.mark();
         container
     }
 
@@ -71,6 +393,8 @@ impl Context {
         surface_config.width = new_size.width;
         surface_config.height = new_size.height;
         self.surface.configure(&self.device, &surface_config);
+        self.changes.mark();
+        self.request_frame();
         for container in self.containers.lock().unwrap().iter() {
             container.resize(self, new_size)
         }
@@ -82,22 +406,104 @@ impl Context {
         }
     }
 
+    fn for_each_script(&self, mut f: impl FnMut(&mut dyn Script)) {
+        let containers = self.containers.lock().unwrap().clone();
+        for script in containers.iter().flat_map(|container| container.scripts()) {
+            with_script(&script, &mut f);
+        }
+    }
+    // Every script is asked, the window stays open if any of them vetoes.
+    pub(crate) fn close_requested(&self) -> bool {
+        let mut close = true;
+        self.for_each_script(|script| close &= script.on_close_requested(self.clone()));
+        close
+    }
+    pub(crate) fn exited(&self) {
+        self.for_each_script(|script| script.on_exit(self.clone()))
+    }
+    pub(crate) fn focus_changed(&self, focused: bool) {
+        self.for_each_script(|script| script.on_focus_changed(self.clone(), focused))
+    }
+    pub(crate) fn suspended(&self) {
+        self.for_each_script(|script| script.on_suspended(self.clone()))
+    }
+    pub(crate) fn resumed(&self) {
+        self.for_each_script(|script| script.on_resumed(self.clone()));
+        self.request_frame()
+    }
+    pub(crate) fn scale_factor_changed(&self, scale_factor: f64) {
+        self.changes.mark();
+        self.for_each_script(|script| script.on_scale_factor_changed(self.clone(), scale_factor))
+    }
+    pub(crate) fn file_dropped(&self, path: PathBuf) {
+        self.for_each_script(|script| script.on_file_dropped(self.clone(), path.clone()))
+    }
+    pub(crate) fn user_event(&self, event: &dyn Any) {
+        self.for_each_script(|script| script.on_user_event(self.clone(), event))
+    }
+
     pub(crate) fn update(&self) {
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         let output_texture = match self.surface.get_current_texture() {
             Ok(v) => v,
             Err(wgpu::SurfaceError::Lost) | Err(wgpu::SurfaceError::Outdated) => return self.resize(self.window.inner_size()),
-            Err(e) => panic!("Error getting current surface texture: {}", e)
+            Err(wgpu::SurfaceError::Timeout) => {
+                log::warn!("{}, skipping frame", Error::from(wgpu::SurfaceError::Timeout));
+                return self.request_frame()
+            }
+            // Releases the render targets, they are recreated on the next frame if memory allows.
+            Err(wgpu::SurfaceError::OutOfMemory) => {
+                log::error!("{}, releasing render targets", Error::from(wgpu::SurfaceError::OutOfMemory));
+                *self.targets.lock().unwrap() = None;
+                return self.resize(self.window.inner_size())
+            }
         };
         let view = output_texture.texture.create_view(&wgpu::TextureViewDescriptor::default());
 
+        self.frame_requested.store(false, Ordering::Relaxed);
+        {
+            let mut deadline = self.frame_deadline.lock().unwrap();
+            if deadline.is_some_and(|v| v <= Instant::now()) { *deadline = None }
+        }
+        let fixed_steps = self.timer.lock().unwrap().begin_frame();
         let containers = self.containers.lock().unwrap().clone();
-        for container in containers {
+        for container in containers.iter() {
+            container.setup_scripts(self)
+        }
+        for _ in 0..fixed_steps {
+            for container in containers.iter() {
+                container.fixed_update(self)
+            }
+        }
+        for container in containers.iter() {
             container.update(self);
-            container.render(self, &mut encoder, &view, true);
         }
-        
+
+        let mut damage = containers.iter().fold(Rect::default(), |damage, container| damage.union(&container.take_damage()));
+        let mut targets = self.targets.lock().unwrap();
+        let (width, height, format, alpha_mode) = {
+            let surface_config = self.surface_config.lock().unwrap();
+            (surface_config.width, surface_config.height, surface_config.format, surface_config.alpha_mode)
+        };
+        let pipelines = self.pipelines();
+        if targets.as_ref().map_or(true, |targets| targets.width != width || targets.height != height || targets.format != format) {
+            *targets = Some(RenderTargets::new(&self.device, &pipelines.texture_layout, format, width, height));
+            damage = Rect::new(0., 0., width as f32, height as f32)
+        }
+        let targets = targets.as_mut().unwrap();
+        let damage = damage.intersect(&Rect::new(0., 0., width as f32, height as f32));
+        if !damage.is_empty() {
+            render_pass(&mut encoder, &targets.get(CANVAS).view, false, &pipelines.clear, &[], 0, Some(damage));
+            for container in containers.iter() {
+                container.render(self, &mut encoder, targets, Some(damage));
+            }
+        }
+        // The canvas is premultiplied, so it is converted for surfaces expecting straight alpha.
+        let blit = if alpha_mode == wgpu::CompositeAlphaMode::PostMultiplied { &pipelines.unpremultiply } else { &pipelines.blit };
+        render_pass(&mut encoder, &view, true, blit, &[&targets.get(CANVAS).bind_group], 0, None);
+
         self.queue.submit(Some(encoder.finish()));
         output_texture.present();
+        self.rendered_version.store(self.version(), Ordering::Relaxed)
     }
 }
\ No newline at end of file
@@ -0,0 +1,659 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::{Element, Style, CoordValue, BackgroundType, TransformFunction, Shadow, FilterFunction, Easing, Iterations, Direction, FillMode, Transition, Animation, Keyframe};
+
+mod selector;   pub use selector::*;
+mod value;      pub use value::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Property {
+    Width,
+    Height,
+    X,
+    Y,
+    Background,
+    Position,
+    Opacity,
+    Transform,
+    TransformOrigin,
+    BorderRadius,
+    BoxShadow,
+    Filter,
+    BackdropFilter,
+    TransitionProperty,
+    TransitionDuration,
+    TransitionTimingFunction,
+    TransitionDelay,
+    AnimationName,
+    AnimationDuration,
+    AnimationTimingFunction,
+    AnimationDelay,
+    AnimationIterationCount,
+    AnimationDirection,
+    AnimationFillMode
+}
+impl Property {
+    pub const STYLE: [Self; 13] = [
+        Self::Width, Self::Height, Self::X, Self::Y, Self::Background, Self::Position, Self::Opacity, Self::Transform,
+        Self::TransformOrigin, Self::BorderRadius, Self::BoxShadow, Self::Filter, Self::BackdropFilter
+    ];
+    pub const ANIMATION: [Self; 7] = [
+        Self::AnimationName, Self::AnimationDuration, Self::AnimationTimingFunction, Self::AnimationDelay,
+        Self::AnimationIterationCount, Self::AnimationDirection, Self::AnimationFillMode
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name.trim().to_ascii_lowercase().as_str() {
+            "width" => Self::Width,
+            "height" => Self::Height,
+            "left" | "x" => Self::X,
+            "top" | "y" => Self::Y,
+            "background" | "background-color" => Self::Background,
+            "position" => Self::Position,
+            "opacity" => Self::Opacity,
+            "transform" => Self::Transform,
+            "transform-origin" => Self::TransformOrigin,
+            "border-radius" => Self::BorderRadius,
+            "box-shadow" => Self::BoxShadow,
+            "filter" => Self::Filter,
+            "backdrop-filter" => Self::BackdropFilter,
+            "transition-property" => Self::TransitionProperty,
+            "transition-duration" => Self::TransitionDuration,
+            "transition-timing-function" => Self::TransitionTimingFunction,
+            "transition-delay" => Self::TransitionDelay,
+            "animation-name" => Self::AnimationName,
+            "animation-duration" => Self::AnimationDuration,
+            "animation-timing-function" => Self::AnimationTimingFunction,
+            "animation-delay" => Self::AnimationDelay,
+            "animation-iteration-count" => Self::AnimationIterationCount,
+            "animation-direction" => Self::AnimationDirection,
+            "animation-fill-mode" => Self::AnimationFillMode,
+            _ => return None
+        })
+    }
+
+    // Transition and animation properties configure the element's animations instead of holding a style value.
+    pub fn is_animation(&self) -> bool {
+        !Self::STYLE.contains(self)
+    }
+
+    pub fn parse_value(&self, src: &str) -> Option<Value> {
+        match src.trim().to_ascii_lowercase().as_str() {
+            "inherit" => return Some(Value::Inherit),
+            "initial" => return Some(Value::Initial),
+            "unset" => return Some(Value::Unset),
+            _ => {}
+        }
+        Some(match self {
+            Self::Width | Self::Height | Self::X | Self::Y | Self::BorderRadius => Value::Coord(parse_coord(src)?),
+            Self::Background => Value::Background(parse_background(src)?),
+            Self::Position => match src.trim().to_ascii_lowercase().as_str() {
+                "absolute" | "fixed" => Value::Position { absolute: true },
+                "relative" | "static" => Value::Position { absolute: false },
+                _ => return None
+            },
+            Self::Opacity => Value::Number(match src.trim().strip_suffix('%') {
+                Some(v) => parse_number(v)? / 100.,
+                None => parse_number(src)?
+            }.clamp(0., 1.)),
+            Self::Transform => Value::Transform(parse_transform(src)?),
+            Self::TransformOrigin => {
+                let (x, y) = parse_transform_origin(src)?;
+                Value::Origin(x, y)
+            }
+            Self::BoxShadow => Value::Shadows(parse_box_shadow(src)?),
+            Self::Filter | Self::BackdropFilter => Value::Filter(parse_filter(src)?),
+            _ => parse_animation_value(*self, src)?
+        })
+    }
+
+    pub fn initial(&self) -> Value {
+        match self {
+            Self::Width | Self::Height | Self::X | Self::Y => Value::Coord(CoordValue::Auto),
+            Self::Background => Value::Background(BackgroundType::None),
+            Self::Position => Value::Position { absolute: false },
+            Self::Opacity => Value::Number(1.),
+            Self::Transform => Value::Transform(Vec::new()),
+            Self::TransformOrigin => Value::Origin(CoordValue::Perc(0.5), CoordValue::Perc(0.5)),
+            Self::BorderRadius => Value::Coord(CoordValue::Px(0.)),
+            Self::BoxShadow => Value::Shadows(Vec::new()),
+            Self::Filter | Self::BackdropFilter => Value::Filter(Vec::new()),
+            Self::TransitionProperty => Value::Properties(Self::STYLE.to_vec()),
+            Self::TransitionDuration | Self::TransitionDelay | Self::AnimationDuration | Self::AnimationDelay => Value::Times(vec![Duration::ZERO]),
+            Self::TransitionTimingFunction | Self::AnimationTimingFunction => Value::Easings(vec![Easing::Ease]),
+            Self::AnimationName => Value::Names(Vec::new()),
+            Self::AnimationIterationCount => Value::Iterations(vec![Iterations::Count(1.)]),
+            Self::AnimationDirection => Value::Directions(vec![Direction::Normal]),
+            Self::AnimationFillMode => Value::FillModes(vec![FillMode::None])
+        }
+    }
+
+    pub fn get(&self, style: &Style) -> Value {
+        match self {
+            Self::Width => Value::Coord(style.width.get()),
+            Self::Height => Value::Coord(style.height.get()),
+            Self::X => Value::Coord(style.x.get()),
+            Self::Y => Value::Coord(style.y.get()),
+            Self::Background => Value::Background(style.background.get()),
+            Self::Position => Value::Position { absolute: style.position.is_absolute() },
+            Self::Opacity => Value::Number(style.opacity.get()),
+            Self::Transform => Value::Transform(style.transform.get()),
+            Self::TransformOrigin => {
+                let (x, y) = style.transform_origin.get();
+                Value::Origin(x, y)
+            }
+            Self::BorderRadius => Value::Coord(style.border_radius.get()),
+            Self::BoxShadow => Value::Shadows(style.box_shadow.get()),
+            Self::Filter => Value::Filter(style.filter.get()),
+            Self::BackdropFilter => Value::Filter(style.backdrop_filter.get()),
+            _ => self.initial()
+        }
+    }
+
+    pub fn set(&self, style: &Style, value: &Value) {
+        match (self, value) {
+            (Self::Width, Value::Coord(v)) => style.width.set(*v),
+            (Self::Height, Value::Coord(v)) => style.height.set(*v),
+            (Self::X, Value::Coord(v)) => style.x.set(*v),
+            (Self::Y, Value::Coord(v)) => style.y.set(*v),
+            (Self::Background, Value::Background(v)) => style.background.set(v.clone()),
+            (Self::Position, Value::Position { absolute: true }) => style.position.set_absolute(),
+            (Self::Position, Value::Position { absolute: false }) => style.position.set_relative(),
+            (Self::Opacity, Value::Number(v)) => style.opacity.set(*v),
+            (Self::Transform, Value::Transform(v)) => style.transform.set(v.clone()),
+            (Self::TransformOrigin, Value::Origin(x, y)) => style.transform_origin.set((*x, *y)),
+            (Self::BorderRadius, Value::Coord(v)) => style.border_radius.set(*v),
+            (Self::BoxShadow, Value::Shadows(v)) => style.box_shadow.set(v.clone()),
+            (Self::Filter, Value::Filter(v)) => style.filter.set(v.clone()),
+            (Self::BackdropFilter, Value::Filter(v)) => style.backdrop_filter.set(v.clone()),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(f32),
+    Coord(CoordValue),
+    Background(BackgroundType),
+    Position { absolute: bool },
+    Transform(Vec<TransformFunction>),
+    Origin(CoordValue, CoordValue),
+    Shadows(Vec<Shadow>),
+    Filter(Vec<FilterFunction>),
+    Properties(Vec<Property>),
+    Times(Vec<Duration>),
+    Easings(Vec<Easing>),
+    Names(Vec<String>),
+    Iterations(Vec<Iterations>),
+    Directions(Vec<Direction>),
+    FillModes(Vec<FillMode>),
+    Inherit,
+    Initial,
+    Unset
+}
+
+#[derive(Clone, Debug)]
+pub struct Declaration {
+    pub property: Property,
+    pub value: Value,
+    pub important: bool
+}
+
+pub fn parse_declarations(src: &str) -> Vec<Declaration> {
+    let mut res = Vec::new();
+    for declaration in split_top_level(src, ';') {
+        let (name, value) = match declaration.split_once(':') {
+            Some(v) => v,
+            None => continue
+        };
+        let (value, important) = match value.trim_end().strip_suffix("important") {
+            Some(v) if v.trim_end().ends_with('!') => (v.trim_end().trim_end_matches('!'), true),
+            _ => (value, false)
+        };
+        let shorthand = match name.trim().to_ascii_lowercase().as_str() {
+            "transition" => Some(parse_transition(value)),
+            "animation" => Some(parse_animation(value)),
+            _ => None
+        };
+        if let Some(longhands) = shorthand {
+            match longhands {
+                Some(longhands) => res.extend(longhands.into_iter().map(|(property, value)| Declaration { property, value, important })),
+                None => log::warn!("Invalid value `{}` for css property `{}`", value.trim(), name.trim())
+            }
+            continue
+        }
+        let property = match Property::parse(name) {
+            Some(v) => v,
+            None => { log::debug!("Unsupported css property `{}`", name.trim()); continue }
+        };
+        match property.parse_value(value) {
+            Some(value) => res.push(Declaration { property, value, important }),
+            None => log::warn!("Invalid value `{}` for css property `{}`", value.trim(), name.trim())
+        }
+    }
+    res
+}
+
+fn split_top_level(src: &str, separator: char) -> Vec<&str> {
+    let mut res = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in src.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                res.push(&src[start..i]);
+                start = i + 1
+            }
+            _ => {}
+        }
+    }
+    res.push(&src[start..]);
+    res
+}
+
+fn strip_comments(src: &str) -> String {
+    let mut res = String::with_capacity(src.len());
+    let mut rest = src;
+    while let Some(start) = rest.find("/*") {
+        res.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + end + 4..],
+            None => ""
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+fn block_end(src: &str) -> usize {
+    let mut depth = 0;
+    for (i, c) in src.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 { return i }
+            }
+            _ => {}
+        }
+    }
+    src.len()
+}
+
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub selectors: Vec<Selector>,
+    pub declarations: Vec<Declaration>
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Stylesheet {
+    pub rules: Vec<Rule>,
+    pub keyframes: HashMap<String, Vec<Keyframe>>
+}
+impl Stylesheet {
+    pub fn parse(src: &str) -> Self {
+        let src = strip_comments(src);
+        let mut res = Self::default();
+        for (prelude, body) in blocks(&src) {
+            if let Some(name) = prelude.strip_prefix("@keyframes") {
+                let name = name.trim().trim_matches(['"', '\'']);
+                res.keyframes.insert(name.to_string(), parse_keyframes(body));
+                continue
+            }
+            if prelude.starts_with('@') {
+                log::debug!("Ignoring css at-rule `{prelude}`");
+                continue
+            }
+            match Selector::parse_list(prelude) {
+                Some(selectors) => res.rules.push(Rule { selectors, declarations: parse_declarations(body) }),
+                None => log::warn!("Ignoring css rule with invalid selector `{prelude}`")
+            }
+        }
+        res
+    }
+}
+
+// Splits source into `prelude { body }` blocks, skipping statements ending with `;` like `@import`.
+fn blocks(src: &str) -> Vec<(&str, &str)> {
+    let mut res = Vec::new();
+    let mut rest = src;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() { break }
+        let open = match rest.find(['{', ';']) {
+            Some(i) => i,
+            None => break
+        };
+        if rest[open..].starts_with(';') {
+            rest = &rest[open + 1..];
+            continue
+        }
+        let end = open + block_end(&rest[open..]);
+        res.push((rest[..open].trim(), &rest[open + 1..end]));
+        rest = &rest[(end + 1).min(rest.len())..];
+    }
+    res
+}
+
+fn parse_keyframes(src: &str) -> Vec<Keyframe> {
+    let mut res: Vec<Keyframe> = Vec::new();
+    for (selectors, body) in blocks(src) {
+        let values: Vec<(Property, Value)> = parse_declarations(body).into_iter()
+            .filter(|d| !d.property.is_animation() && !matches!(d.value, Value::Inherit | Value::Initial | Value::Unset))
+            .map(|d| (d.property, d.value))
+            .collect();
+        for selector in selectors.split(',') {
+            let offset = match selector.trim().to_ascii_lowercase().as_str() {
+                "from" => 0.,
+                "to" => 1.,
+                v => match v.strip_suffix('%').and_then(parse_number) {
+                    Some(v) if (0. ..=100.).contains(&v) => v / 100.,
+                    _ => { log::warn!("Ignoring invalid keyframe selector `{}`", selector.trim()); continue }
+                }
+            };
+            res.push(Keyframe { offset, values: values.clone() })
+        }
+    }
+    res
+}
+
+// Returns whether any of the `<style>` elements changed since the last cascade.
+fn collect_style_elements(e: &Element, sheets: &mut Vec<Stylesheet>) -> bool {
+    let mut changed = false;
+    if e.get_tag() == "style" {
+        sheets.push(Stylesheet::parse(&e.get_text_content()));
+        changed = e.has_style_dirty()
+    }
+    for child in e.get_children() {
+        changed |= collect_style_elements(&child, sheets)
+    }
+    changed
+}
+
+// Restyles the dirty elements with their descendants and the siblings after them, which sibling selectors
+// can depend on. `dirty` restyles the whole subtree.
+fn cascade(e: &Element, sheets: &[Stylesheet], parent: Option<&Element>, dirty: bool) {
+    let dirty = e.take_style_dirty() || dirty;
+    if dirty { cascade_element(e, sheets, parent) }
+    let mut dirty_siblings = dirty;
+    for child in e.get_children() {
+        dirty_siblings |= child.is_style_dirty();
+        cascade(&child, sheets, Some(e), dirty_siblings)
+    }
+}
+
+fn cascade_element(e: &Element, sheets: &[Stylesheet], parent: Option<&Element>) {
+    let inline = e.get_attribute("style").map(|v| parse_declarations(&v)).unwrap_or_default();
+    let mut matched = Vec::new();
+    let mut order = 0;
+    for rule in sheets.iter().flat_map(|s| s.rules.iter()) {
+        order += 1;
+        let specificity = match rule.selectors.iter().filter(|s| s.matches(e)).map(|s| s.specificity()).max() {
+            Some(v) => v,
+            None => continue
+        };
+        for d in rule.declarations.iter() {
+            matched.push(((d.important, false, specificity, order), d))
+        }
+    }
+    for d in inline.iter() {
+        matched.push(((d.important, true, Specificity::default(), order), d))
+    }
+    matched.sort_by_key(|(key, _)| *key);
+
+    let mut values = HashMap::new();
+    for (_, d) in matched {
+        values.insert(d.property, d.value.clone());
+    }
+    // A value that is neither the one the cascade last set nor one an animation set was set by a script. Like an
+    // inline style, it then takes precedence over the stylesheets.
+    let set_by_script = |property: Property, cascaded: Option<&Value>| {
+        let current = property.get(&e.style);
+        current != cascaded.cloned().unwrap_or_else(|| property.initial()) && e.get_animated_value(property).as_ref() != Some(&current)
+    };
+    let mut cascaded = e.cascaded();
+    let previous = std::mem::take(&mut *cascaded);
+    let mut overridden: Vec<Property> = previous.iter()
+        .filter(|(property, value)| value.as_ref().map_or(true, |v| set_by_script(*property, Some(v))))
+        .map(|(property, _)| *property)
+        .collect();
+    for (property, _) in previous.iter() {
+        if !values.contains_key(property) && !overridden.contains(property) {
+            property.set(&e.style, &property.initial())
+        }
+    }
+    let mut animation_values = HashMap::new();
+    for (property, value) in values {
+        let value = match (value, parent) {
+            (Value::Inherit, Some(parent)) => property.get(&parent.style),
+            (Value::Inherit | Value::Initial | Value::Unset, _) => property.initial(),
+            (v, _) => v
+        };
+        if property.is_animation() {
+            animation_values.insert(property, value);
+            continue
+        }
+        if !previous.iter().any(|(p, _)| *p == property) && set_by_script(property, None) {
+            overridden.push(property)
+        }
+        if overridden.contains(&property) { continue }
+        property.set(&e.style, &value);
+        cascaded.push((property, Some(value)))
+    }
+    cascaded.extend(overridden.into_iter().map(|property| (property, None)));
+    drop(cascaded);
+    let (transitions, animations) = css_animations(&animation_values, sheets);
+    e.set_css_animations(transitions, animations)
+}
+
+// Builds the element's transitions and keyframe animations, list values repeat to match the length of
+// `transition-property` and `animation-name` like in browsers.
+fn css_animations(values: &HashMap<Property, Value>, sheets: &[Stylesheet]) -> (Vec<Transition>, Vec<(String, Animation)>) {
+    let get = |property: Property| values.get(&property).cloned().unwrap_or_else(|| property.initial());
+    fn nth<T: Copy>(list: &[T], i: usize, default: T) -> T {
+        if list.is_empty() { default } else { list[i % list.len()] }
+    }
+    let times = |property| match get(property) { Value::Times(v) => v, _ => Vec::new() };
+    let easings = |property| match get(property) { Value::Easings(v) => v, _ => Vec::new() };
+
+    let properties = match get(Property::TransitionProperty) { Value::Properties(v) => v, _ => Vec::new() };
+    let (durations, delays, easing) = (times(Property::TransitionDuration), times(Property::TransitionDelay), easings(Property::TransitionTimingFunction));
+    let transitions = properties.into_iter().enumerate()
+        .map(|(i, property)| Transition::new(property, nth(&durations, i, Duration::ZERO))
+            .easing(nth(&easing, i, Easing::Ease))
+            .delay(nth(&delays, i, Duration::ZERO)))
+        .filter(|t| !t.duration.is_zero() || !t.delay.is_zero())
+        .collect();
+
+    let names = match get(Property::AnimationName) { Value::Names(v) => v, _ => Vec::new() };
+    let (durations, delays, easing) = (times(Property::AnimationDuration), times(Property::AnimationDelay), easings(Property::AnimationTimingFunction));
+    let iterations = match get(Property::AnimationIterationCount) { Value::Iterations(v) => v, _ => Vec::new() };
+    let directions = match get(Property::AnimationDirection) { Value::Directions(v) => v, _ => Vec::new() };
+    let fill_modes = match get(Property::AnimationFillMode) { Value::FillModes(v) => v, _ => Vec::new() };
+    let animations = names.into_iter().enumerate().filter_map(|(i, name)| {
+        let keyframes = match sheets.iter().rev().find_map(|s| s.keyframes.get(&name)) {
+            Some(v) => v,
+            None => { log::debug!("No @keyframes named `{name}`"); return None }
+        };
+        let animation = keyframes.iter().fold(Animation::new(nth(&durations, i, Duration::ZERO)), |a, k| a.keyframe(k.offset, &k.values))
+            .easing(nth(&easing, i, Easing::Ease))
+            .delay(nth(&delays, i, Duration::ZERO))
+            .iterations(nth(&iterations, i, Iterations::Count(1.)))
+            .direction(nth(&directions, i, Direction::Normal))
+            .fill_mode(nth(&fill_modes, i, FillMode::None));
+        Some((name, animation))
+    }).collect();
+    (transitions, animations)
+}
+
+pub fn apply(root: &Element, sheets: &[Stylesheet]) {
+    let mut sheets = sheets.to_vec();
+    collect_style_elements(root, &mut sheets);
+    cascade(root, &sheets, root.get_parent().as_ref(), true)
+}
+// Like `apply`, but only restyles the parts of the tree that changed since the last cascade.
+pub(crate) fn apply_dirty(root: &Element, sheets: &[Stylesheet]) {
+    let mut sheets = sheets.to_vec();
+    let changed = collect_style_elements(root, &mut sheets);
+    cascade(root, &sheets, root.get_parent().as_ref(), changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html;
+
+    fn cascaded_width(css: &str, inline: &str) -> CoordValue {
+        let root = html::parse(&format!(r#"<div id="a" class="b" style="{inline}"></div>"#));
+        apply(&root, &[Stylesheet::parse(css)]);
+        root.get_children()[0].style.width.get()
+    }
+
+    #[test]
+    fn cascade_order() {
+        let cases = [
+            ("div { width: 1px } div { width: 2px }", "", CoordValue::Px(2.)),
+            ("#a { width: 1px } .b { width: 2px } div { width: 3px }", "", CoordValue::Px(1.)),
+            ("div.b { width: 1px } .b { width: 2px }", "", CoordValue::Px(1.)),
+            ("#a { width: 1px }", "width: 2px", CoordValue::Px(2.)),
+            ("div { width: 1px !important } #a { width: 2px }", "width: 3px", CoordValue::Px(1.)),
+            ("#a { width: 1px !important }", "width: 2px !important", CoordValue::Px(2.)),
+            ("div { width: 1px !important } div { width: 2px !important }", "", CoordValue::Px(2.)),
+            ("/* #a { width: 1px } */ div { width: 50% }", "", CoordValue::Perc(0.5))
+        ];
+        for (css, inline, expected) in cases {
+            assert_eq!(cascaded_width(css, inline), expected, "{css} / {inline}");
+        }
+    }
+
+    #[test]
+    fn declarations() {
+        let declarations = parse_declarations("width: 10px; unknown: 1; height: nope; x: 5px ! important");
+        assert_eq!(declarations.len(), 2);
+        assert_eq!(declarations[0].value, Value::Coord(CoordValue::Px(10.)));
+        assert!(!declarations[0].important);
+        assert_eq!(declarations[1].property, Property::X);
+        assert!(declarations[1].important);
+    }
+
+    #[test]
+    fn transition_shorthand() {
+        let declarations = parse_declarations("transition: opacity 0.3s ease-in, width 200ms linear 1s");
+        let value = |property| declarations.iter().find(|d| d.property == property).map(|d| d.value.clone());
+        assert_eq!(value(Property::TransitionProperty), Some(Value::Properties(vec![Property::Opacity, Property::Width])));
+        assert_eq!(value(Property::TransitionDuration), Some(Value::Times(vec![Duration::from_millis(300), Duration::from_millis(200)])));
+        assert_eq!(value(Property::TransitionTimingFunction), Some(Value::Easings(vec![Easing::EaseIn, Easing::Linear])));
+        assert_eq!(value(Property::TransitionDelay), Some(Value::Times(vec![Duration::ZERO, Duration::from_secs(1)])));
+        assert!(parse_declarations("transition: opacity 1s 1s 1s").is_empty());
+        assert_eq!(parse_declarations("transition-timing-function: cubic-bezier(0.1, 0.7, 1, 0.1)")[0].value, Value::Easings(vec![Easing::CubicBezier(0.1, 0.7, 1., 0.1)]));
+    }
+
+    #[test]
+    fn animation_shorthand() {
+        let declarations = parse_declarations("animation: 2s infinite alternate both spin");
+        let value = |property| declarations.iter().find(|d| d.property == property).map(|d| d.value.clone());
+        assert_eq!(value(Property::AnimationName), Some(Value::Names(vec!["spin".into()])));
+        assert_eq!(value(Property::AnimationDuration), Some(Value::Times(vec![Duration::from_secs(2)])));
+        assert_eq!(value(Property::AnimationIterationCount), Some(Value::Iterations(vec![Iterations::Infinite])));
+        assert_eq!(value(Property::AnimationDirection), Some(Value::Directions(vec![Direction::Alternate])));
+        assert_eq!(value(Property::AnimationFillMode), Some(Value::FillModes(vec![FillMode::Both])));
+    }
+
+    #[test]
+    fn keyframes() {
+        let sheet = Stylesheet::parse("@keyframes fade { from { opacity: 0 } 50%, 75% { opacity: 0.5 } to { opacity: 1; animation-name: x } } div { width: 1px }");
+        assert_eq!(sheet.rules.len(), 1);
+        let offsets: Vec<f32> = sheet.keyframes["fade"].iter().map(|k| k.offset).collect();
+        assert_eq!(offsets, [0., 0.5, 0.75, 1.]);
+        assert_eq!(sheet.keyframes["fade"][3].values, [(Property::Opacity, Value::Number(1.))]);
+    }
+
+    #[test]
+    fn cascaded_animations() {
+        let root = html::parse(r#"<div id="a"></div>"#);
+        let e = root.get_children()[0].clone();
+        let sheet = Stylesheet::parse("
+            @keyframes grow { from { width: 0px } to { width: 100px } }
+            #a { transition: opacity 1s linear; animation: grow 1s linear forwards }
+        ");
+        apply(&root, std::slice::from_ref(&sheet));
+        assert_eq!(e.get_transitions(), [Transition::new(Property::Opacity, Duration::from_secs(1)).easing(Easing::Linear)]);
+        e.tick_animations(Duration::ZERO);
+        e.tick_animations(Duration::from_millis(500));
+        assert_eq!(e.style.width.get(), CoordValue::Px(50.));
+
+        // Restyling keeps the running animation instead of restarting it.
+        apply(&root, std::slice::from_ref(&sheet));
+        e.tick_animations(Duration::from_secs(2));
+        assert_eq!(e.style.width.get(), CoordValue::Px(100.));
+        assert!(!e.is_animating());
+
+        apply(&root, &[Stylesheet::parse("#a { width: 10px }")]);
+        assert!(e.get_transitions().is_empty());
+        assert_eq!(e.style.width.get(), CoordValue::Px(10.));
+    }
+
+    #[test]
+    fn interaction_pseudo_classes() {
+        let sheet = Stylesheet::parse("a:hover, div { width: 1px } div:focus { width: 2px } :not(:active) { height: 3px }");
+        assert_eq!(sheet.rules.len(), 3);
+        let root = html::parse("<div></div>");
+        apply(&root, &[sheet]);
+        let e = root.get_children()[0].clone();
+        assert_eq!(e.style.width.get(), CoordValue::Px(1.));
+        assert_eq!(e.style.height.get(), CoordValue::Px(3.));
+    }
+
+    #[test]
+    fn dirty_subtrees() {
+        let root = html::parse(r#"<div id="a"><p></p></div><div id="b"></div><div id="c"></div>"#);
+        let [a, b, c]: [Element; 3] = root.get_children().try_into().ok().unwrap();
+        let p = a.get_children()[0].clone();
+        apply(&root, &[Stylesheet::parse("div, p { width: 1px }")]);
+        let widths = || [&a, &p, &b, &c].map(|e| e.style.width.get());
+        let sheets = [Stylesheet::parse("div, p { width: 2px }")];
+
+        apply_dirty(&root, &sheets);
+        assert_eq!(widths(), [CoordValue::Px(1.); 4]);
+        b.set_attribute("class", "x");
+        apply_dirty(&root, &sheets);
+        assert_eq!(widths(), [CoordValue::Px(1.), CoordValue::Px(1.), CoordValue::Px(2.), CoordValue::Px(2.)]);
+        a.set_attribute("class", "x");
+        apply_dirty(&root, &sheets);
+        assert_eq!(widths(), [CoordValue::Px(2.); 4]);
+        assert!(!root.has_style_dirty());
+    }
+
+    #[test]
+    fn style_elements() {
+        let root = html::parse("<style>div { width: 1px }</style><div></div>");
+        let (style, e) = (root.get_children()[0].clone(), root.get_children()[1].clone());
+        apply_dirty(&root, &[]);
+        assert_eq!(e.style.width.get(), CoordValue::Px(1.));
+        style.remove();
+        apply_dirty(&root, &[]);
+        assert_eq!(e.style.width.get(), CoordValue::Auto);
+    }
+
+    #[test]
+    fn script_overrides() {
+        let root = html::parse(r#"<div style="x: 4px"></div>"#);
+        let e = root.get_children()[0].clone();
+        e.style.height.set_px(9.);
+        let sheets = [Stylesheet::parse("div { width: 1px; height: 2px; y: 3px }")];
+        apply(&root, &sheets);
+        assert_eq!(e.style.height.get(), CoordValue::Px(9.));
+        assert_eq!(e.style.width.get(), CoordValue::Px(1.));
+
+        e.style.width.set_px(5.);
+        apply(&root, &sheets);
+        apply(&root, &[Stylesheet::parse("div { width: 6px }")]);
+        assert_eq!(e.style.width.get(), CoordValue::Px(5.));
+        assert_eq!(e.style.y.get(), CoordValue::Auto);
+        assert_eq!(e.style.x.get(), CoordValue::Px(4.));
+    }
+}
@@ -0,0 +1,443 @@
+use crate::Element;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Specificity(pub u32, pub u32, pub u32);
+impl std::ops::Add for Specificity {
+    type Output = Self;
+    fn add(self, o: Self) -> Self {
+        Self(self.0 + o.0, self.1 + o.1, self.2 + o.2)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeOperator {
+    Exists,
+    Equals(String),
+    Includes(String),
+    DashMatch(String),
+    Prefix(String),
+    Suffix(String),
+    Substring(String)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PseudoClass {
+    Root,
+    Empty,
+    FirstChild,
+    LastChild,
+    OnlyChild,
+    NthChild(i32, i32),
+    NthLastChild(i32, i32),
+    Not(Vec<Selector>),
+    // Interaction states are parsed so rules using them stay valid, but elements never match them yet.
+    Hover,
+    Focus,
+    Active
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompoundSelector {
+    pub tag: Option<String>,
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+    pub attributes: Vec<(String, AttributeOperator)>,
+    pub pseudo_classes: Vec<PseudoClass>
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Combinator {
+    Descendant,
+    Child,
+    NextSibling,
+    SubsequentSibling
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selector {
+    pub subject: CompoundSelector,
+    pub ancestors: Vec<(Combinator, CompoundSelector)>
+}
+impl Selector {
+    pub fn parse(src: &str) -> Option<Self> {
+        let mut p = SelectorParser { src, pos: 0 };
+        let res = p.selector()?;
+        p.skip_whitespace();
+        if p.pos == src.len() { Some(res) } else { None }
+    }
+    pub fn parse_list(src: &str) -> Option<Vec<Self>> {
+        let mut p = SelectorParser { src, pos: 0 };
+        let res = p.selector_list()?;
+        if p.pos == src.len() { Some(res) } else { None }
+    }
+
+    pub fn specificity(&self) -> Specificity {
+        self.ancestors.iter().fold(self.subject.specificity(), |acc, (_, c)| acc + c.specificity())
+    }
+
+    pub fn matches(&self, element: &Element) -> bool {
+        self.subject.matches(element) && matches_ancestors(&self.ancestors, element)
+    }
+}
+
+fn matches_ancestors(ancestors: &[(Combinator, CompoundSelector)], element: &Element) -> bool {
+    let ((combinator, compound), rest) = match ancestors.split_first() {
+        Some(v) => v,
+        None => return true
+    };
+    match combinator {
+        Combinator::Child => match element.get_parent() {
+            Some(parent) => compound.matches(&parent) && matches_ancestors(rest, &parent),
+            None => false
+        },
+        Combinator::Descendant => {
+            let mut current = element.get_parent();
+            while let Some(parent) = current {
+                if compound.matches(&parent) && matches_ancestors(rest, &parent) { return true }
+                current = parent.get_parent();
+            }
+            false
+        }
+        Combinator::NextSibling => match element.get_previous_element_sibling() {
+            Some(sibling) => compound.matches(&sibling) && matches_ancestors(rest, &sibling),
+            None => false
+        },
+        Combinator::SubsequentSibling => {
+            let mut current = element.get_previous_element_sibling();
+            while let Some(sibling) = current {
+                if compound.matches(&sibling) && matches_ancestors(rest, &sibling) { return true }
+                current = sibling.get_previous_element_sibling();
+            }
+            false
+        }
+    }
+}
+
+impl CompoundSelector {
+    pub fn specificity(&self) -> Specificity {
+        let mut res = Specificity(
+            self.id.is_some() as u32,
+            (self.classes.len() + self.attributes.len()) as u32,
+            self.tag.is_some() as u32
+        );
+        for pseudo in self.pseudo_classes.iter() {
+            res = res + match pseudo {
+                PseudoClass::Not(list) => list.iter().map(|s| s.specificity()).max().unwrap_or_default(),
+                _ => Specificity(0, 1, 0)
+            }
+        }
+        res
+    }
+
+    pub fn matches(&self, e: &Element) -> bool {
+        if e.is_text() { return false }
+        if let Some(tag) = &self.tag {
+            if *tag != e.get_tag() { return false }
+        }
+        if let Some(id) = &self.id {
+            if Some(id) != e.get_id().as_ref() { return false }
+        }
+        if !self.classes.is_empty() {
+            let classes = e.get_classes();
+            if !self.classes.iter().all(|c| classes.contains(c)) { return false }
+        }
+        for (name, op) in self.attributes.iter() {
+            let value = match e.get_attribute(name) { Some(v) => v, None => return false };
+            let ok = match op {
+                AttributeOperator::Exists => true,
+                AttributeOperator::Equals(v) => value == *v,
+                AttributeOperator::Includes(v) => value.split_ascii_whitespace().any(|w| w == v),
+                AttributeOperator::DashMatch(v) => value == *v || value.starts_with(&format!("{v}-")),
+                AttributeOperator::Prefix(v) => !v.is_empty() && value.starts_with(v.as_str()),
+                AttributeOperator::Suffix(v) => !v.is_empty() && value.ends_with(v.as_str()),
+                AttributeOperator::Substring(v) => !v.is_empty() && value.contains(v.as_str())
+            };
+            if !ok { return false }
+        }
+        self.pseudo_classes.iter().all(|pseudo| match pseudo {
+            PseudoClass::Root => e.get_parent().is_none(),
+            PseudoClass::Empty => e.get_children().is_empty() && e.get_text().is_empty(),
+            PseudoClass::FirstChild => e.get_previous_element_sibling().is_none(),
+            PseudoClass::LastChild => e.get_next_element_sibling().is_none(),
+            PseudoClass::OnlyChild => e.get_previous_element_sibling().is_none() && e.get_next_element_sibling().is_none(),
+            PseudoClass::NthChild(a, b) => nth_matches(*a, *b, child_index(e, false)),
+            PseudoClass::NthLastChild(a, b) => nth_matches(*a, *b, child_index(e, true)),
+            PseudoClass::Not(list) => !list.iter().any(|s| s.matches(e)),
+            PseudoClass::Hover | PseudoClass::Focus | PseudoClass::Active => false
+        })
+    }
+}
+
+fn child_index(e: &Element, from_end: bool) -> i32 {
+    let siblings: Vec<_> = match e.get_parent() {
+        Some(p) => p.get_children().into_iter().filter(|s| !s.is_text()).collect(),
+        None => return 1
+    };
+    let i = siblings.iter().position(|s| s == e).unwrap_or_default();
+    if from_end { (siblings.len() - i) as i32 } else { i as i32 + 1 }
+}
+
+fn nth_matches(a: i32, b: i32, index: i32) -> bool {
+    if a == 0 { return index == b }
+    let n = index - b;
+    n % a == 0 && n / a >= 0
+}
+
+struct SelectorParser<'a> {
+    src: &'a str,
+    pos: usize
+}
+impl SelectorParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_whitespace()) { self.bump(); }
+        self.pos != start
+    }
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) { self.bump(); true } else { false }
+    }
+    fn ident(&mut self) -> Option<String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || matches!(c, '-' | '_') || !c.is_ascii()) {
+            self.bump();
+        }
+        if start == self.pos { None } else { Some(self.src[start..self.pos].to_string()) }
+    }
+    fn string_or_ident(&mut self) -> Option<String> {
+        match self.peek()? {
+            quote @ ('"' | '\'') => {
+                self.bump();
+                let start = self.pos;
+                let len = self.src[start..].find(quote)?;
+                self.pos += len + 1;
+                Some(self.src[start..start + len].to_string())
+            }
+            _ => self.ident()
+        }
+    }
+
+    fn selector_list(&mut self) -> Option<Vec<Selector>> {
+        let mut res = vec![self.selector()?];
+        loop {
+            self.skip_whitespace();
+            if !self.eat(',') { return Some(res) }
+            res.push(self.selector()?)
+        }
+    }
+
+    fn selector(&mut self) -> Option<Selector> {
+        self.skip_whitespace();
+        let mut compounds = vec![self.compound()?];
+        let mut combinators = Vec::new();
+        loop {
+            let had_space = self.skip_whitespace();
+            let combinator = match self.peek() {
+                Some('>') => Combinator::Child,
+                Some('+') => Combinator::NextSibling,
+                Some('~') => Combinator::SubsequentSibling,
+                Some(',') | Some(')') | None => break,
+                _ if had_space => Combinator::Descendant,
+                _ => return None
+            };
+            if combinator != Combinator::Descendant {
+                self.bump();
+                self.skip_whitespace();
+            }
+            combinators.push(combinator);
+            compounds.push(self.compound()?);
+        }
+        let subject = compounds.pop().unwrap();
+        let ancestors = combinators.into_iter().rev().zip(compounds.into_iter().rev()).collect();
+        Some(Selector { subject, ancestors })
+    }
+
+    fn compound(&mut self) -> Option<CompoundSelector> {
+        let mut res = CompoundSelector::default();
+        let universal = self.eat('*');
+        if !universal {
+            res.tag = self.ident().map(|tag| tag.to_ascii_lowercase())
+        }
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                Some('#') => { self.bump(); res.id = Some(self.ident()?) }
+                Some('.') => { self.bump(); res.classes.push(self.ident()?) }
+                Some('[') => { self.bump(); res.attributes.push(self.attribute()?) }
+                Some(':') => { self.bump(); res.pseudo_classes.push(self.pseudo_class()?) }
+                _ => break
+            }
+        }
+        if self.pos == start && res.tag.is_none() && !universal {
+            return None
+        }
+        Some(res)
+    }
+
+    fn attribute(&mut self) -> Option<(String, AttributeOperator)> {
+        self.skip_whitespace();
+        let name = self.ident()?.to_ascii_lowercase();
+        self.skip_whitespace();
+        if self.eat(']') { return Some((name, AttributeOperator::Exists)) }
+        let op: fn(String) -> AttributeOperator = match self.bump()? {
+            '=' => AttributeOperator::Equals,
+            c @ ('~' | '|' | '^' | '$' | '*') if self.eat('=') => match c {
+                '~' => AttributeOperator::Includes,
+                '|' => AttributeOperator::DashMatch,
+                '^' => AttributeOperator::Prefix,
+                '$' => AttributeOperator::Suffix,
+                _ => AttributeOperator::Substring
+            },
+            _ => return None
+        };
+        self.skip_whitespace();
+        let value = self.string_or_ident()?;
+        self.skip_whitespace();
+        if !self.eat(']') { return None }
+        Some((name, op(value)))
+    }
+
+    fn pseudo_class(&mut self) -> Option<PseudoClass> {
+        let name = self.ident()?.to_ascii_lowercase();
+        let res = match name.as_str() {
+            "root" => PseudoClass::Root,
+            "empty" => PseudoClass::Empty,
+            "first-child" => PseudoClass::FirstChild,
+            "last-child" => PseudoClass::LastChild,
+            "only-child" => PseudoClass::OnlyChild,
+            "hover" => PseudoClass::Hover,
+            "focus" => PseudoClass::Focus,
+            "active" => PseudoClass::Active,
+            "nth-child" | "nth-last-child" | "not" => {
+                if !self.eat('(') { return None }
+                let arg = if name == "not" {
+                    let list = self.selector_list()?;
+                    PseudoClass::Not(list)
+                } else {
+                    let end = self.src[self.pos..].find(')')?;
+                    let (a, b) = parse_nth(&self.src[self.pos..self.pos + end])?;
+                    self.pos += end;
+                    if name == "nth-child" { PseudoClass::NthChild(a, b) } else { PseudoClass::NthLastChild(a, b) }
+                };
+                self.skip_whitespace();
+                if !self.eat(')') { return None }
+                arg
+            }
+            _ => return None
+        };
+        Some(res)
+    }
+}
+
+fn parse_nth(src: &str) -> Option<(i32, i32)> {
+    let src: String = src.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_ascii_lowercase();
+    match src.as_str() {
+        "odd" => return Some((2, 1)),
+        "even" => return Some((2, 0)),
+        _ => {}
+    }
+    match src.split_once('n') {
+        Some((a, b)) => {
+            let a = match a {
+                "" | "+" => 1,
+                "-" => -1,
+                a => a.parse().ok()?
+            };
+            let b = if b.is_empty() { 0 } else { b.strip_prefix('+').unwrap_or(b).parse().ok()? };
+            Some((a, b))
+        }
+        None => Some((0, src.parse().ok()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html;
+
+    #[test]
+    fn parse() {
+        let selector = Selector::parse("ul > li.a.b#c[href^=\"x\"]:nth-child(2n+1)").unwrap();
+        assert_eq!(selector.subject, CompoundSelector {
+            tag: Some("li".into()),
+            id: Some("c".into()),
+            classes: vec!["a".into(), "b".into()],
+            attributes: vec![("href".into(), AttributeOperator::Prefix("x".into()))],
+            pseudo_classes: vec![PseudoClass::NthChild(2, 1)]
+        });
+        assert_eq!(selector.ancestors, vec![(Combinator::Child, CompoundSelector { tag: Some("ul".into()), ..Default::default() })]);
+
+        let combinators = Selector::parse("a b + c ~ d").unwrap().ancestors.iter().map(|(c, _)| *c).collect::<Vec<_>>();
+        assert_eq!(combinators, [Combinator::SubsequentSibling, Combinator::NextSibling, Combinator::Descendant]);
+        assert_eq!(Selector::parse_list("a, .b , #c").unwrap().len(), 3);
+        for invalid in ["", "a >", "[href", ".", "a:unknown", "a,"] {
+            assert!(Selector::parse_list(invalid).is_none(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn specificity() {
+        let cases = [
+            ("*", Specificity(0, 0, 0)),
+            ("div", Specificity(0, 0, 1)),
+            (".a[href]", Specificity(0, 2, 0)),
+            ("#a", Specificity(1, 0, 0)),
+            ("div p.a", Specificity(0, 1, 2)),
+            ("li:first-child", Specificity(0, 1, 1)),
+            ("a:hover", Specificity(0, 1, 1)),
+            (":not(#a, .b)", Specificity(1, 0, 0)),
+            ("#a > ul li:nth-child(odd)", Specificity(1, 1, 2))
+        ];
+        for (src, expected) in cases {
+            assert_eq!(Selector::parse(src).unwrap().specificity(), expected, "{src}");
+        }
+    }
+
+    #[test]
+    fn matches() {
+        let root = html::parse(r#"<div id="main"><ul><li class="a">1<li class="a b" lang="en-US">2<li>3</ul><p></p></div>"#);
+        let count = |src: &str| {
+            let selector = Selector::parse(src).unwrap();
+            let mut stack = root.get_children();
+            let mut count = 0;
+            while let Some(e) = stack.pop() {
+                count += selector.matches(&e) as usize;
+                stack.extend(e.get_children())
+            }
+            count
+        };
+        let cases = [
+            ("li", 3),
+            ("#main li", 3),
+            ("div > li", 0),
+            ("ul > li.a", 2),
+            (".a.b", 1),
+            ("li + li", 2),
+            (".a ~ li:not(.a)", 1),
+            ("li:first-child", 1),
+            ("li:last-child", 1),
+            ("li:nth-child(2n+1)", 2),
+            ("li:nth-last-child(1)", 1),
+            ("p:empty", 1),
+            ("ul:only-child", 0),
+            ("[lang|=en]", 1),
+            ("[class~=b]", 1),
+            ("[class$=b]", 1),
+            ("[class*=\" \"]", 1),
+            ("li:hover", 0),
+            ("ul :focus", 0),
+            ("li:active", 0),
+            ("li:not(:hover)", 3)
+        ];
+        for (src, expected) in cases {
+            assert_eq!(count(src), expected, "{src}");
+        }
+    }
+}
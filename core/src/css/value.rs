@@ -0,0 +1,448 @@
+use std::time::Duration;
+
+use super::{Property, Value};
+use crate::{Color, CoordValue, BackgroundType, Matrix, TransformFunction, Shadow, FilterFunction, Easing, Iterations, Direction, FillMode};
+
+pub fn parse_number(src: &str) -> Option<f32> {
+    let v: f32 = src.trim().parse().ok()?;
+    if v.is_finite() { Some(v) } else { None }
+}
+
+pub fn parse_coord(src: &str) -> Option<CoordValue> {
+    let src = src.trim().to_ascii_lowercase();
+    if src == "auto" { return Some(CoordValue::Auto) }
+    if let Some(v) = src.strip_suffix("px") { return Some(CoordValue::Px(parse_number(v)?)) }
+    if let Some(v) = src.strip_suffix('%') { return Some(CoordValue::Perc(parse_number(v)? / 100.)) }
+    if parse_number(&src)? == 0. { Some(CoordValue::Px(0.)) } else { None }
+}
+
+pub fn parse_background(src: &str) -> Option<BackgroundType> {
+    match src.trim().to_ascii_lowercase().as_str() {
+        "none" => Some(BackgroundType::None),
+        v => Some(BackgroundType::Solid(parse_color(v)?))
+    }
+}
+
+fn parse_channel(src: &str, scale: f32) -> Option<f32> {
+    let src = src.trim();
+    let v = match src.strip_suffix('%') {
+        Some(v) => parse_number(v)? / 100.,
+        None => parse_number(src)? / scale
+    };
+    Some(v.clamp(0., 1.))
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|v| (v * 17) as f32 / 255.);
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok().map(|v| v as f32 / 255.);
+    if !hex.is_ascii() { return None }
+    Some(match hex.len() {
+        3 => Color::new(digit(0)?, digit(1)?, digit(2)?, 1.),
+        4 => Color::new(digit(0)?, digit(1)?, digit(2)?, digit(3)?),
+        6 => Color::new(byte(0)?, byte(2)?, byte(4)?, 1.),
+        8 => Color::new(byte(0)?, byte(2)?, byte(4)?, byte(6)?),
+        _ => return None
+    })
+}
+
+fn parse_function_color(args: &str) -> Option<Color> {
+    let (channels, alpha) = match args.split_once('/') {
+        Some((c, a)) => (c, Some(a)),
+        None => (args, None)
+    };
+    let mut parts: Vec<&str> = if channels.contains(',') {
+        channels.split(',').collect()
+    } else {
+        channels.split_ascii_whitespace().collect()
+    };
+    let alpha = match (alpha, parts.len()) {
+        (Some(a), 3) => parse_channel(a, 1.)?,
+        (None, 4) => parse_channel(parts.pop()?, 1.)?,
+        (None, 3) => 1.,
+        _ => return None
+    };
+    Some(Color::new(parse_channel(parts[0], 255.)?, parse_channel(parts[1], 255.)?, parse_channel(parts[2], 255.)?, alpha))
+}
+
+pub fn parse_color(src: &str) -> Option<Color> {
+    let src = src.trim().to_ascii_lowercase();
+    if let Some(hex) = src.strip_prefix('#') { return parse_hex(hex) }
+    if let Some(args) = src.strip_prefix("rgba(").or_else(|| src.strip_prefix("rgb(")) {
+        return parse_function_color(args.strip_suffix(')')?)
+    }
+    let rgb = |r: u8, g: u8, b: u8| Color::new(r as f32 / 255., g as f32 / 255., b as f32 / 255., 1.);
+    Some(match src.as_str() {
+        "transparent" => Color::TRANSPARENT,
+        "black" => Color::BLACK,
+        "white" => Color::WHITE,
+        "red" => Color::RED,
+        "lime" => Color::GREEN,
+        "blue" => Color::BLUE,
+        "yellow" => Color::YELLOW,
+        "gray" | "grey" => rgb(128, 128, 128),
+        "silver" => rgb(192, 192, 192),
+        "maroon" => rgb(128, 0, 0),
+        "green" => rgb(0, 128, 0),
+        "navy" => rgb(0, 0, 128),
+        "olive" => rgb(128, 128, 0),
+        "purple" => rgb(128, 0, 128),
+        "teal" => rgb(0, 128, 128),
+        "aqua" | "cyan" => rgb(0, 255, 255),
+        "fuchsia" | "magenta" => rgb(255, 0, 255),
+        "orange" => rgb(255, 165, 0),
+        "pink" => rgb(255, 192, 203),
+        "brown" => rgb(165, 42, 42),
+        "gold" => rgb(255, 215, 0),
+        "indigo" => rgb(75, 0, 130),
+        "violet" => rgb(238, 130, 238),
+        "coral" => rgb(255, 127, 80),
+        "salmon" => rgb(250, 128, 114),
+        "tomato" => rgb(255, 99, 71),
+        "crimson" => rgb(220, 20, 60),
+        "orchid" => rgb(218, 112, 214),
+        "khaki" => rgb(240, 230, 140),
+        "beige" => rgb(245, 245, 220),
+        "ivory" => rgb(255, 255, 240),
+        "lavender" => rgb(230, 230, 250),
+        "turquoise" => rgb(64, 224, 208),
+        "skyblue" => rgb(135, 206, 235),
+        "steelblue" => rgb(70, 130, 180),
+        "royalblue" => rgb(65, 105, 225),
+        "darkblue" => rgb(0, 0, 139),
+        "darkgreen" => rgb(0, 100, 0),
+        "darkred" => rgb(139, 0, 0),
+        "darkgray" | "darkgrey" => rgb(169, 169, 169),
+        "lightgray" | "lightgrey" => rgb(211, 211, 211),
+        "lightblue" => rgb(173, 216, 230),
+        "lightgreen" => rgb(144, 238, 144),
+        "whitesmoke" => rgb(245, 245, 245),
+        "dimgray" | "dimgrey" => rgb(105, 105, 105),
+        "slategray" | "slategrey" => rgb(112, 128, 144),
+        _ => return None
+    })
+}
+
+pub fn parse_angle(src: &str) -> Option<f32> {
+    let src = src.trim().to_ascii_lowercase();
+    if let Some(v) = src.strip_suffix("deg") { return Some(parse_number(v)?.to_radians()) }
+    if let Some(v) = src.strip_suffix("grad") { return Some(parse_number(v)? * std::f32::consts::PI / 200.) }
+    if let Some(v) = src.strip_suffix("rad") { return parse_number(v) }
+    if let Some(v) = src.strip_suffix("turn") { return Some(parse_number(v)? * std::f32::consts::TAU) }
+    if parse_number(&src)? == 0. { Some(0.) } else { None }
+}
+
+fn parse_args(args: &str) -> Vec<&str> {
+    if args.contains(',') {
+        args.split(',').map(|v| v.trim()).collect()
+    } else {
+        args.split_ascii_whitespace().collect()
+    }
+}
+
+fn parse_transform_function(name: &str, args: &str) -> Option<TransformFunction> {
+    let args = parse_args(args);
+    let number = |i: usize| parse_number(args.get(i)?);
+    let angle = |i: usize| parse_angle(args.get(i)?);
+    let coord = |i: usize| parse_coord(args.get(i)?);
+    Some(match (name, args.len()) {
+        ("translate", 1) => TransformFunction::Translate(coord(0)?, CoordValue::Px(0.)),
+        ("translate", 2) => TransformFunction::Translate(coord(0)?, coord(1)?),
+        ("translatex", 1) => TransformFunction::Translate(coord(0)?, CoordValue::Px(0.)),
+        ("translatey", 1) => TransformFunction::Translate(CoordValue::Px(0.), coord(0)?),
+        ("scale", 1) => TransformFunction::Scale(number(0)?, number(0)?),
+        ("scale", 2) => TransformFunction::Scale(number(0)?, number(1)?),
+        ("scalex", 1) => TransformFunction::Scale(number(0)?, 1.),
+        ("scaley", 1) => TransformFunction::Scale(1., number(0)?),
+        ("rotate", 1) => TransformFunction::Rotate(angle(0)?),
+        ("skew", 1) => TransformFunction::Skew(angle(0)?, 0.),
+        ("skew", 2) => TransformFunction::Skew(angle(0)?, angle(1)?),
+        ("skewx", 1) => TransformFunction::Skew(angle(0)?, 0.),
+        ("skewy", 1) => TransformFunction::Skew(0., angle(0)?),
+        ("matrix", 6) => TransformFunction::Matrix(Matrix::new(number(0)?, number(1)?, number(2)?, number(3)?, number(4)?, number(5)?)),
+        _ => return None
+    })
+}
+
+pub fn parse_transform(src: &str) -> Option<Vec<TransformFunction>> {
+    let src = src.trim().to_ascii_lowercase();
+    if src == "none" { return Some(Vec::new()) }
+    let mut res = Vec::new();
+    let mut rest = src.as_str();
+    while !rest.is_empty() {
+        let (name, tail) = rest.split_once('(')?;
+        let (args, tail) = tail.split_once(')')?;
+        res.push(parse_transform_function(name.trim(), args)?);
+        rest = tail.trim_start()
+    }
+    if res.is_empty() { None } else { Some(res) }
+}
+
+pub fn parse_transform_origin(src: &str) -> Option<(CoordValue, CoordValue)> {
+    // Keywords carry their axis, `Some(true)` being vertical, so `top left` can be swapped into place.
+    let keyword = |v: &str| Some(match v.to_ascii_lowercase().as_str() {
+        "left" => (CoordValue::Perc(0.), Some(false)),
+        "right" => (CoordValue::Perc(1.), Some(false)),
+        "top" => (CoordValue::Perc(0.), Some(true)),
+        "bottom" => (CoordValue::Perc(1.), Some(true)),
+        "center" => (CoordValue::Perc(0.5), None),
+        _ => return None
+    });
+    let value = |v: &str| keyword(v).map(|(v, _)| v).or_else(|| parse_coord(v));
+    let parts: Vec<&str> = src.split_ascii_whitespace().collect();
+    match parts.as_slice() {
+        [v] => match keyword(v) {
+            Some((v, Some(true))) => Some((CoordValue::Perc(0.5), v)),
+            _ => Some((value(v)?, CoordValue::Perc(0.5)))
+        },
+        [x, y] => {
+            let swap = matches!(keyword(x), Some((_, Some(true)))) || matches!(keyword(y), Some((_, Some(false))));
+            if swap { Some((value(y)?, value(x)?)) } else { Some((value(x)?, value(y)?)) }
+        }
+        _ => None
+    }
+}
+
+pub fn parse_length(src: &str) -> Option<f32> {
+    let src = src.trim().to_ascii_lowercase();
+    if let Some(v) = src.strip_suffix("px") { return parse_number(v) }
+    if parse_number(&src)? == 0. { Some(0.) } else { None }
+}
+
+fn split_whitespace_top_level(src: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+    for (i, c) in src.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(s) = start.take() { res.push(&src[s..i]) }
+                continue
+            }
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(s) = start { res.push(&src[s..]) }
+    res
+}
+
+fn parse_shadow(src: &str) -> Option<Shadow> {
+    let mut lengths = Vec::new();
+    let mut color = None;
+    let mut inset = false;
+    for part in split_whitespace_top_level(src) {
+        if part.eq_ignore_ascii_case("inset") && !inset {
+            inset = true
+        } else if let Some(v) = parse_length(part) {
+            lengths.push(v)
+        } else if color.is_none() {
+            color = Some(parse_color(part)?)
+        } else {
+            return None
+        }
+    }
+    if lengths.len() < 2 || lengths.len() > 4 { return None }
+    Some(Shadow {
+        x: lengths[0],
+        y: lengths[1],
+        blur: lengths.get(2).copied().unwrap_or(0.).max(0.),
+        spread: lengths.get(3).copied().unwrap_or(0.),
+        color: color.unwrap_or(Color::BLACK),
+        inset
+    })
+}
+
+pub fn parse_box_shadow(src: &str) -> Option<Vec<Shadow>> {
+    if src.trim().eq_ignore_ascii_case("none") { return Some(Vec::new()) }
+    super::split_top_level(src, ',').into_iter().map(parse_shadow).collect()
+}
+
+pub fn parse_filter(src: &str) -> Option<Vec<FilterFunction>> {
+    let src = src.trim().to_ascii_lowercase();
+    if src == "none" { return Some(Vec::new()) }
+    let mut res = Vec::new();
+    let mut rest = src.as_str();
+    while !rest.is_empty() {
+        let (name, tail) = rest.split_once('(')?;
+        let (args, tail) = tail.split_once(')')?;
+        res.push(match name.trim() {
+            "blur" if args.trim().is_empty() => FilterFunction::Blur(0.),
+            "blur" => FilterFunction::Blur(parse_length(args)?.max(0.)),
+            _ => return None
+        });
+        rest = tail.trim_start()
+    }
+    if res.is_empty() { None } else { Some(res) }
+}
+
+pub fn parse_time(src: &str) -> Option<Duration> {
+    let src = src.trim().to_ascii_lowercase();
+    let (v, scale) = match src.strip_suffix("ms") {
+        Some(v) => (v, 1000.),
+        None => match src.strip_suffix('s') {
+            Some(v) => (v, 1.),
+            None if parse_number(&src)? == 0. => return Some(Duration::ZERO),
+            None => return None
+        }
+    };
+    let v: f64 = v.trim().parse().ok()?;
+    Duration::try_from_secs_f64(v / scale).ok()
+}
+
+pub fn parse_easing(src: &str) -> Option<Easing> {
+    let src = src.trim().to_ascii_lowercase();
+    Some(match src.as_str() {
+        "linear" => Easing::Linear,
+        "ease" => Easing::Ease,
+        "ease-in" => Easing::EaseIn,
+        "ease-out" => Easing::EaseOut,
+        "ease-in-out" => Easing::EaseInOut,
+        _ => {
+            let (name, args) = src.strip_suffix(')')?.split_once('(')?;
+            let args = parse_args(args).into_iter().map(parse_number).collect::<Option<Vec<f32>>>()?;
+            match (name.trim(), args.as_slice()) {
+                ("cubic-bezier", &[x1, y1, x2, y2]) if (0. ..=1.).contains(&x1) && (0. ..=1.).contains(&x2) => Easing::CubicBezier(x1, y1, x2, y2),
+                ("spring", &[stiffness, damping]) => Easing::Spring { stiffness, damping, mass: 1. },
+                ("spring", &[stiffness, damping, mass]) => Easing::Spring { stiffness, damping, mass },
+                _ => return None
+            }
+        }
+    })
+}
+
+fn parse_iterations(src: &str) -> Option<Iterations> {
+    if src.trim().eq_ignore_ascii_case("infinite") { return Some(Iterations::Infinite) }
+    let v = parse_number(src)?;
+    if v >= 0. { Some(Iterations::Count(v)) } else { None }
+}
+
+fn parse_direction(src: &str) -> Option<Direction> {
+    Some(match src.trim().to_ascii_lowercase().as_str() {
+        "normal" => Direction::Normal,
+        "reverse" => Direction::Reverse,
+        "alternate" => Direction::Alternate,
+        "alternate-reverse" => Direction::AlternateReverse,
+        _ => return None
+    })
+}
+
+fn parse_fill_mode(src: &str) -> Option<FillMode> {
+    Some(match src.trim().to_ascii_lowercase().as_str() {
+        "none" => FillMode::None,
+        "forwards" => FillMode::Forwards,
+        "backwards" => FillMode::Backwards,
+        "both" => FillMode::Both,
+        _ => return None
+    })
+}
+
+fn parse_name(src: &str) -> Option<String> {
+    let src = src.trim().trim_matches(['"', '\'']);
+    let valid = !src.is_empty() && src.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| src.to_string())
+}
+
+fn parse_list<T>(src: &str, f: impl Fn(&str) -> Option<T>) -> Option<Vec<T>> {
+    super::split_top_level(src, ',').into_iter().map(f).collect()
+}
+
+// Unknown property names are skipped instead of invalidating the list, like browsers do.
+fn parse_transition_property(src: &str) -> Option<Vec<Property>> {
+    match src.trim().to_ascii_lowercase().as_str() {
+        "none" => Some(Vec::new()),
+        "all" => Some(Property::STYLE.to_vec()),
+        _ => Some(super::split_top_level(src, ',').into_iter().filter_map(Property::parse).filter(|p| !p.is_animation()).collect())
+    }
+}
+
+pub(super) fn parse_animation_value(property: Property, src: &str) -> Option<Value> {
+    Some(match property {
+        Property::TransitionProperty => Value::Properties(parse_transition_property(src)?),
+        Property::TransitionDuration | Property::TransitionDelay | Property::AnimationDuration | Property::AnimationDelay => Value::Times(parse_list(src, parse_time)?),
+        Property::TransitionTimingFunction | Property::AnimationTimingFunction => Value::Easings(parse_list(src, parse_easing)?),
+        Property::AnimationName if src.trim().eq_ignore_ascii_case("none") => Value::Names(Vec::new()),
+        Property::AnimationName => Value::Names(parse_list(src, parse_name)?),
+        Property::AnimationIterationCount => Value::Iterations(parse_list(src, parse_iterations)?),
+        Property::AnimationDirection => Value::Directions(parse_list(src, parse_direction)?),
+        Property::AnimationFillMode => Value::FillModes(parse_list(src, parse_fill_mode)?),
+        _ => return None
+    })
+}
+
+// Expands the `transition` shorthand into its longhands, the first time being the duration and the second the delay.
+pub fn parse_transition(src: &str) -> Option<Vec<(Property, Value)>> {
+    let (mut properties, mut durations, mut easings, mut delays) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for item in super::split_top_level(src, ',') {
+        let (mut property, mut duration, mut easing, mut delay) = (None, None, None, None);
+        for part in split_whitespace_top_level(item) {
+            if let Some(v) = parse_time(part) {
+                if duration.is_none() { duration = Some(v) } else if delay.is_none() { delay = Some(v) } else { return None }
+            } else if let (None, Some(v)) = (easing, parse_easing(part)) {
+                easing = Some(v)
+            } else if property.is_none() {
+                property = Some(parse_transition_property(part)?)
+            } else {
+                return None
+            }
+        }
+        properties.extend(property.unwrap_or_else(|| Property::STYLE.to_vec()));
+        let count = properties.len() - durations.len();
+        durations.extend(std::iter::repeat(duration.unwrap_or_default()).take(count));
+        easings.extend(std::iter::repeat(easing.unwrap_or(Easing::Ease)).take(count));
+        delays.extend(std::iter::repeat(delay.unwrap_or_default()).take(count));
+    }
+    Some(vec![
+        (Property::TransitionProperty, Value::Properties(properties)),
+        (Property::TransitionDuration, Value::Times(durations)),
+        (Property::TransitionTimingFunction, Value::Easings(easings)),
+        (Property::TransitionDelay, Value::Times(delays))
+    ])
+}
+
+// Expands the `animation` shorthand, keywords are matched first so the remaining identifier is the name.
+pub fn parse_animation(src: &str) -> Option<Vec<(Property, Value)>> {
+    if src.trim().eq_ignore_ascii_case("none") {
+        return Some(Property::ANIMATION.iter().map(|p| (*p, p.initial())).collect())
+    }
+    let (mut names, mut durations, mut easings, mut delays) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    let (mut iterations, mut directions, mut fill_modes) = (Vec::new(), Vec::new(), Vec::new());
+    for item in super::split_top_level(src, ',') {
+        let (mut name, mut duration, mut easing, mut delay) = (None, None, None, None);
+        let (mut iteration, mut direction, mut fill_mode) = (None, None, None);
+        for part in split_whitespace_top_level(item) {
+            if let Some(v) = parse_time(part) {
+                if duration.is_none() { duration = Some(v) } else if delay.is_none() { delay = Some(v) } else { return None }
+            } else if let (None, Some(v)) = (easing, parse_easing(part)) {
+                easing = Some(v)
+            } else if let (None, Some(v)) = (iteration, parse_iterations(part)) {
+                iteration = Some(v)
+            } else if let (None, Some(v)) = (direction, parse_direction(part)) {
+                direction = Some(v)
+            } else if let (None, Some(v)) = (fill_mode, parse_fill_mode(part)) {
+                fill_mode = Some(v)
+            } else if name.is_none() {
+                name = Some(parse_name(part)?)
+            } else {
+                return None
+            }
+        }
+        names.push(name?);
+        durations.push(duration.unwrap_or_default());
+        easings.push(easing.unwrap_or(Easing::Ease));
+        delays.push(delay.unwrap_or_default());
+        iterations.push(iteration.unwrap_or(Iterations::Count(1.)));
+        directions.push(direction.unwrap_or_default());
+        fill_modes.push(fill_mode.unwrap_or_default());
+    }
+    Some(vec![
+        (Property::AnimationName, Value::Names(names)),
+        (Property::AnimationDuration, Value::Times(durations)),
+        (Property::AnimationTimingFunction, Value::Easings(easings)),
+        (Property::AnimationDelay, Value::Times(delays)),
+        (Property::AnimationIterationCount, Value::Iterations(iterations)),
+        (Property::AnimationDirection, Value::Directions(directions)),
+        (Property::AnimationFillMode, Value::FillModes(fill_modes))
+    ])
+}
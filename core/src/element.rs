@@ -1,90 +1,584 @@
-use std::sync::{Arc, Mutex};
+use std::{sync::{Arc, Mutex, MutexGuard, Weak, atomic::{AtomicBool, Ordering}}, collections::HashMap, time::Duration};
 
-use wgpu::RenderPass;
+use crate::{Coord, WindowSize, Rect, Background, CoordValue, BackgroundType, Position, Opacity, Transform, TransformOrigin, Matrix, html, css, EventType, EventListener, ElementEvent, Context, Effect, Transition, Animation, AnimationState, Color, Shadow, BoxShadow, Filter, DrawOp, utils::Changes, shader::{self, DisplayList, RenderCommand, OP_RECT, OP_SHADOW, OP_INSET_SHADOW, OP_BACKDROP, OP_FILL_NON_ZERO, OP_FILL_EVEN_ODD}};
 
-use crate::{Coord, WindowSize, Background, CoordValue, BackgroundType, Position};
+#[derive(Default)]
+struct Node {
+    style: Arc<Style>,
+    tag: Mutex<String>,
+    attributes: Mutex<HashMap<String, String>>,
+    text: Mutex<String>,
+    parent: Mutex<Weak<Node>>,
+    children: Mutex<Vec<Element>>,
+    cascaded: Mutex<Vec<(css::Property, Option<css::Value>)>>,
+    style_dirty: AtomicBool,
+    listeners: Mutex<Vec<(EventType, EventListener)>>,
+    bindings: Mutex<Vec<Effect>>,
+    animations: Mutex<AnimationState>,
+    drawing: Mutex<Vec<DrawOp>>,
+    image_src: Mutex<Option<String>>
+}
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct Element {
     pub style: Arc<Style>,
-    parent: Arc<Mutex<Option<Element>>>,
-    children: Arc<Mutex<Vec<Element>>>
+    node: Arc<Node>
+}
+impl Default for Element {
+    fn default() -> Self {
+        Self::from_node(Arc::default())
+    }
+}
+impl PartialEq for Element {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.node, &other.node)
+    }
 }
 impl Element {
-    pub(crate) fn set_scissor_rect(&self, render_pass: &mut RenderPass, ws: WindowSize) {
-        render_pass.set_scissor_rect(
-            self.get_x(ws) as u32,
-            self.get_y(ws) as u32,
-            self.get_width(ws) as u32,
-            self.get_height(ws) as u32
-        )
+    fn from_node(node: Arc<Node>) -> Self {
+        Self { style: node.style.clone(), node }
+    }
+    // Identifies the element for as long as it is alive.
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.node) as usize
+    }
+
+    pub fn get_rect(&self, ws: WindowSize) -> Rect {
+        let x = self.get_x(ws);
+        let y = self.get_y(ws);
+        Rect::new(x, y, x + self.get_width(ws), y + self.get_height(ws))
+    }
+
+    pub fn contains(&self, other: &Element) -> bool {
+        let mut current = Some(other.clone());
+        while let Some(e) = current {
+            if e == *self { return true }
+            current = e.get_parent()
+        }
+        false
+    }
+    fn adopt(&self, child: &Element) -> bool {
+        if child.contains(self) {
+            log::warn!("Cannot insert an element inside itself or one of its descendants");
+            return false
+        }
+        child.remove();
+        *child.node.parent.lock().unwrap() = Arc::downgrade(&self.node);
+        child.style.changes.set_parent(Some(&self.style.changes));
+        true
     }
 
     pub fn add_child(&self, child: Self) {
-        self.children.lock().unwrap().push(child)
+        if self.adopt(&child) {
+            self.node.children.lock().unwrap().push(child);
+            self.mark_tree_changed()
+        }
     }
     pub fn create_child(&self) -> Self {
         let child = Self::default();
-        child.set_parent(Some(self.clone()));
         self.add_child(child.clone());
         child
     }
+    pub fn insert_before(&self, child: Self, reference: Option<&Element>) -> bool {
+        let reference = match reference {
+            Some(v) => v,
+            None => {
+                self.add_child(child.clone());
+                return child.get_parent().as_ref() == Some(self)
+            }
+        };
+        if reference.get_parent().as_ref() != Some(self) { return false }
+        if child == *reference { return true }
+        if !self.adopt(&child) { return false }
+        let mut children = self.node.children.lock().unwrap();
+        let i = children.iter().position(|c| c == reference).unwrap_or(children.len());
+        children.insert(i, child);
+        drop(children);
+        self.mark_tree_changed();
+        true
+    }
+    pub fn replace_child(&self, new_child: Self, old_child: &Element) -> bool {
+        if old_child.get_parent().as_ref() != Some(self) { return false }
+        if new_child == *old_child { return true }
+        if !self.adopt(&new_child) { return false }
+        let mut children = self.node.children.lock().unwrap();
+        let i = children.iter().position(|c| c == old_child).unwrap();
+        children[i] = new_child;
+        drop(children);
+        old_child.detach();
+        self.mark_tree_changed();
+        true
+    }
+    pub fn remove_child(&self, child: &Element) -> bool {
+        if child.get_parent().as_ref() != Some(self) { return false }
+        child.remove();
+        true
+    }
+    pub fn remove_children(&self) {
+        let children = std::mem::take(&mut *self.node.children.lock().unwrap());
+        for child in children {
+            child.detach()
+        }
+        self.mark_tree_changed()
+    }
+    pub fn remove(&self) {
+        let parent = match self.get_parent() { Some(v) => v, None => return };
+        parent.node.children.lock().unwrap().retain(|c| c != self);
+        parent.mark_tree_changed();
+        self.detach()
+    }
+    // Clears the parent pointer once the element is out of its parent's children.
+    fn detach(&self) {
+        // The rules of a removed `<style>` element applied to the whole tree it was in.
+        if self.get_tag() == "style" || self.query_selector("style").is_some() {
+            let mut root = self.clone();
+            while let Some(parent) = root.get_parent() { root = parent }
+            root.mark_style_dirty()
+        }
+        *self.node.parent.lock().unwrap() = Weak::new();
+        self.style.changes.set_parent(None)
+    }
 
     pub fn get_parent(&self) -> Option<Element> {
-        self.parent.lock().unwrap().clone()
+        self.node.parent.lock().unwrap().upgrade().map(Self::from_node)
     }
     pub fn set_parent(&self, parent: Option<Element>) {
-        *self.parent.lock().unwrap() = parent
-    }
-
-    fn get_data_loop(&self, ws: WindowSize, data: &mut Vec<f32>) {
-        match self.style.background.get() {
-            BackgroundType::Solid(bg) => {
-                if bg.a > 0. {
-                    let x = self.get_x(ws);
-                    let y = self.get_y(ws);
-                    let width = self.get_width(ws);
-                    let height = self.get_height(ws);
-                    data.append(&mut vec![
-                        1., x, x + width, y, y+height,
-                        bg.r, bg.g, bg.b, bg.a
-                    ])
-                }
-            }
-            BackgroundType::None => {}
+        match parent {
+            Some(parent) => parent.add_child(self.clone()),
+            None => self.remove()
         }
-        for child in self.children.lock().unwrap().iter() {
-            child.get_data_loop(ws, data)
+    }
+    pub fn get_children(&self) -> Vec<Element> {
+        self.node.children.lock().unwrap().clone()
+    }
+    pub fn children(&self) -> impl Iterator<Item = Element> {
+        self.get_children().into_iter()
+    }
+    pub fn get_first_child(&self) -> Option<Element> {
+        self.node.children.lock().unwrap().first().cloned()
+    }
+    pub fn get_last_child(&self) -> Option<Element> {
+        self.node.children.lock().unwrap().last().cloned()
+    }
+    fn get_sibling(&self, offset: isize) -> Option<Element> {
+        let siblings = self.get_parent()?.get_children();
+        let i = siblings.iter().position(|s| s == self)?;
+        siblings.get(i.checked_add_signed(offset)?).cloned()
+    }
+    pub fn get_previous_sibling(&self) -> Option<Element> {
+        self.get_sibling(-1)
+    }
+    pub fn get_next_sibling(&self) -> Option<Element> {
+        self.get_sibling(1)
+    }
+    fn get_element_sibling(&self, offset: isize) -> Option<Element> {
+        let mut current = self.get_sibling(offset);
+        while let Some(sibling) = current {
+            if !sibling.is_text() { return Some(sibling) }
+            current = sibling.get_sibling(offset)
         }
+        None
+    }
+    pub fn get_previous_element_sibling(&self) -> Option<Element> {
+        self.get_element_sibling(-1)
     }
-    pub(crate) fn get_data(&self, ws: WindowSize) -> Vec<f32> {
-        let mut data = Vec::new();
-        self.get_data_loop(ws, &mut data);
-        data
+    pub fn get_next_element_sibling(&self) -> Option<Element> {
+        self.get_element_sibling(1)
     }
 
-    pub fn get_content_width(&self) -> f32 {
-        let mut res = 0.;
-        for child in self.children.lock().unwrap().iter() {
-            res += match child.style.width.get() {
-                CoordValue::Px(v) => v,
-                CoordValue::Perc(_) => 0.,
-                CoordValue::Auto => self.get_content_width()
+    pub(crate) fn mark_style_dirty(&self) {
+        self.node.style_dirty.store(true, Ordering::Relaxed)
+    }
+    fn mark_tree_changed(&self) {
+        self.mark_style_dirty();
+        self.mark_changed()
+    }
+    pub(crate) fn mark_changed(&self) {
+        self.style.changes.mark()
+    }
+    pub(crate) fn get_version(&self) -> u64 {
+        self.style.changes.version()
+    }
+    // Properties set by the cascade with their value, or None for those a script set instead.
+    pub(crate) fn cascaded(&self) -> MutexGuard<'_, Vec<(css::Property, Option<css::Value>)>> {
+        self.node.cascaded.lock().unwrap()
+    }
+    pub(crate) fn is_style_dirty(&self) -> bool {
+        self.node.style_dirty.load(Ordering::Relaxed)
+    }
+    pub(crate) fn take_style_dirty(&self) -> bool {
+        self.node.style_dirty.swap(false, Ordering::Relaxed)
+    }
+    pub(crate) fn has_style_dirty(&self) -> bool {
+        self.is_style_dirty() || self.node.children.lock().unwrap().iter().any(|c| c.has_style_dirty())
+    }
+
+    pub fn get_tag(&self) -> String {
+        self.node.tag.lock().unwrap().clone()
+    }
+    pub fn set_tag(&self, tag: &str) {
+        *self.node.tag.lock().unwrap() = tag.to_ascii_lowercase();
+        self.mark_style_dirty()
+    }
+
+    pub fn get_attribute(&self, name: &str) -> Option<String> {
+        self.node.attributes.lock().unwrap().get(name).cloned()
+    }
+    pub fn has_attribute(&self, name: &str) -> bool {
+        self.node.attributes.lock().unwrap().contains_key(name)
+    }
+    pub fn set_attribute(&self, name: &str, value: &str) {
+        self.node.attributes.lock().unwrap().insert(name.to_ascii_lowercase(), value.to_string());
+        self.mark_style_dirty()
+    }
+    pub fn remove_attribute(&self, name: &str) {
+        self.node.attributes.lock().unwrap().remove(name);
+        self.mark_style_dirty()
+    }
+    pub fn get_attributes(&self) -> HashMap<String, String> {
+        self.node.attributes.lock().unwrap().clone()
+    }
+
+    pub fn get_id(&self) -> Option<String> {
+        self.get_attribute("id")
+    }
+    pub fn set_id(&self, id: &str) {
+        self.set_attribute("id", id)
+    }
+
+    pub fn get_classes(&self) -> Vec<String> {
+        match self.node.attributes.lock().unwrap().get("class") {
+            Some(v) => v.split_ascii_whitespace().map(String::from).collect(),
+            None => Vec::new()
+        }
+    }
+    pub fn set_classes(&self, classes: &[&str]) {
+        self.set_attribute("class", &classes.join(" "))
+    }
+    pub fn has_class(&self, class: &str) -> bool {
+        self.get_classes().iter().any(|c| c == class)
+    }
+    pub fn add_class(&self, class: &str) {
+        let mut classes = self.get_classes();
+        if classes.iter().any(|c| c == class) { return }
+        classes.push(class.to_string());
+        self.set_attribute("class", &classes.join(" "))
+    }
+    pub fn remove_class(&self, class: &str) {
+        let mut classes = self.get_classes();
+        let len = classes.len();
+        classes.retain(|c| c != class);
+        if classes.len() != len {
+            self.set_attribute("class", &classes.join(" "))
+        }
+    }
+    pub fn toggle_class(&self, class: &str) -> bool {
+        let has_class = self.has_class(class);
+        if has_class { self.remove_class(class) } else { self.add_class(class) }
+        !has_class
+    }
+
+    fn find_descendants(&self, selectors: &[css::Selector], res: &mut Vec<Element>, first_only: bool) {
+        for child in self.get_children().iter() {
+            if first_only && !res.is_empty() { return }
+            if selectors.iter().any(|s| s.matches(child)) {
+                res.push(child.clone())
             }
+            child.find_descendants(selectors, res, first_only)
         }
+    }
+    fn query(&self, selector: &str, first_only: bool) -> Vec<Element> {
+        let selectors = match css::Selector::parse_list(selector) {
+            Some(v) => v,
+            None => {
+                log::warn!("Invalid selector `{selector}`");
+                return Vec::new()
+            }
+        };
+        let mut res = Vec::new();
+        self.find_descendants(&selectors, &mut res, first_only);
         res
     }
-    pub fn get_content_height(&self) -> f32 {
-        let mut res = 0.;
-        for child in self.children.lock().unwrap().iter() {
-            res += match child.style.height.get() {
-                CoordValue::Px(v) => v,
-                CoordValue::Perc(_) => 0.,
-                CoordValue::Auto => self.get_content_height()
+    pub fn query_selector(&self, selector: &str) -> Option<Element> {
+        self.query(selector, true).into_iter().next()
+    }
+    pub fn query_selector_all(&self, selector: &str) -> Vec<Element> {
+        self.query(selector, false)
+    }
+    pub fn get_element_by_id(&self, id: &str) -> Option<Element> {
+        for child in self.node.children.lock().unwrap().iter() {
+            if child.get_id().as_deref() == Some(id) { return Some(child.clone()) }
+            if let Some(v) = child.get_element_by_id(id) { return Some(v) }
+        }
+        None
+    }
+    pub fn matches(&self, selector: &str) -> bool {
+        match css::Selector::parse_list(selector) {
+            Some(selectors) => selectors.iter().any(|s| s.matches(self)),
+            None => false
+        }
+    }
+
+    pub fn get_text(&self) -> String {
+        self.node.text.lock().unwrap().clone()
+    }
+    pub fn set_text(&self, text: &str) {
+        let mut current = self.node.text.lock().unwrap();
+        if *current != text {
+            *current = text.to_string();
+            self.mark_changed()
+        }
+    }
+    // Text runs are anonymous `#text` children, so they keep their order between the other children.
+    pub fn is_text(&self) -> bool {
+        self.get_tag() == "#text"
+    }
+    pub(crate) fn push_text(&self, text: &str) {
+        let last = self.get_last_child().filter(|c| c.is_text());
+        let node = last.unwrap_or_else(|| {
+            let node = Self::default();
+            node.set_tag("#text");
+            self.add_child(node.clone());
+            node
+        });
+        node.node.text.lock().unwrap().push_str(text);
+        node.mark_tree_changed()
+    }
+    // The own text followed by the text of every descendant, in tree order.
+    pub fn get_text_content(&self) -> String {
+        let mut res = self.get_text();
+        for child in self.get_children() {
+            res += &child.get_text_content()
+        }
+        res
+    }
+
+    pub fn bind(&self, mut f: impl FnMut(&Element) + Send + 'static) {
+        let node = Arc::downgrade(&self.node);
+        let effect = Effect::new(move || {
+            if let Some(node) = node.upgrade() { f(&Element::from_node(node)) }
+        });
+        self.node.bindings.lock().unwrap().push(effect)
+    }
+    pub fn bind_text(&self, f: impl Fn() -> String + Send + 'static) {
+        self.bind(move |e| e.set_text(&f()))
+    }
+    pub fn bind_children(&self, f: impl Fn() -> Vec<Element> + Send + 'static) {
+        self.bind(move |e| {
+            let children = f();
+            if children != e.get_children() {
+                e.remove_children();
+                for child in children { e.add_child(child) }
+            }
+        })
+    }
+    pub fn clear_bindings(&self) {
+        self.node.bindings.lock().unwrap().clear()
+    }
+
+    pub fn add_event_listener(&self, kind: EventType, listener: impl Fn(Context, &ElementEvent) + Send + Sync + 'static) {
+        self.node.listeners.lock().unwrap().push((kind, Arc::new(listener)))
+    }
+    pub fn on_click(&self, listener: impl Fn(Context, &ElementEvent) + Send + Sync + 'static) {
+        self.add_event_listener(EventType::Click, listener)
+    }
+    pub fn remove_event_listeners(&self, kind: EventType) {
+        self.node.listeners.lock().unwrap().retain(|(k, _)| *k != kind)
+    }
+    pub(crate) fn get_event_listeners(&self, kind: EventType) -> Vec<EventListener> {
+        self.node.listeners.lock().unwrap().iter().filter(|(k, _)| *k == kind).map(|(_, l)| l.clone()).collect()
+    }
+
+    pub fn get_transform_matrix(&self, ws: WindowSize) -> Matrix {
+        if self.style.transform.is_none() { return Matrix::IDENTITY }
+        let r = self.get_rect(ws);
+        let (width, height) = (r.x1 - r.x0, r.y1 - r.y0);
+        let resolve = |v: CoordValue, size: f32| match v {
+            CoordValue::Px(v) => v,
+            CoordValue::Perc(v) => v * size,
+            CoordValue::Auto => size / 2.
+        };
+        let (ox, oy) = self.style.transform_origin.get();
+        let (ox, oy) = (r.x0 + resolve(ox, width), r.y0 + resolve(oy, height));
+        Matrix::translate(ox, oy)
+            .multiply(&self.style.transform.get_matrix(width, height))
+            .multiply(&Matrix::translate(-ox, -oy))
+    }
+    pub fn get_world_transform(&self, ws: WindowSize) -> Matrix {
+        let local = self.get_transform_matrix(ws);
+        match self.get_parent() {
+            Some(parent) => parent.get_world_transform(ws).multiply(&local),
+            None => local
+        }
+    }
+    fn contains_world_point(&self, ws: WindowSize, world: &Matrix, x: f32, y: f32) -> bool {
+        match world.inverse() {
+            Some(inverse) => {
+                let (x, y) = inverse.apply(x, y);
+                self.get_rect(ws).contains(x, y)
             }
+            None => false
+        }
+    }
+    pub fn contains_point(&self, ws: WindowSize, x: f32, y: f32) -> bool {
+        self.contains_world_point(ws, &self.get_world_transform(ws), x, y)
+    }
+    fn hit_test_loop(&self, ws: WindowSize, x: f32, y: f32, parent: &Matrix) -> Option<Element> {
+        let world = parent.multiply(&self.get_transform_matrix(ws));
+        for child in self.get_children().iter().rev() {
+            if let Some(v) = child.hit_test_loop(ws, x, y, &world) { return Some(v) }
+        }
+        if self.contains_world_point(ws, &world, x, y) { Some(self.clone()) } else { None }
+    }
+    pub fn hit_test(&self, ws: WindowSize, x: f32, y: f32) -> Option<Element> {
+        let parent = self.get_parent().map(|p| p.get_world_transform(ws)).unwrap_or_default();
+        self.hit_test_loop(ws, x, y, &parent)
+    }
+
+    pub fn set_transition(&self, transition: Transition) {
+        self.node.animations.lock().unwrap().set_transition(transition)
+    }
+    pub fn remove_transition(&self, property: css::Property) {
+        self.node.animations.lock().unwrap().remove_transition(property)
+    }
+    pub fn get_transitions(&self) -> Vec<Transition> {
+        self.node.animations.lock().unwrap().get_transitions()
+    }
+    pub fn animate(&self, animation: Animation) {
+        self.node.animations.lock().unwrap().animate(&self.style, animation);
+        self.mark_changed()
+    }
+    pub(crate) fn set_css_animations(&self, transitions: Vec<Transition>, animations: Vec<(String, Animation)>) {
+        if self.node.animations.lock().unwrap().set_css(&self.style, transitions, animations) {
+            self.mark_changed()
+        }
+    }
+    pub fn cancel_animations(&self) {
+        self.node.animations.lock().unwrap().cancel_animations(&self.style)
+    }
+    pub fn is_animating(&self) -> bool {
+        self.node.animations.lock().unwrap().is_animating()
+    }
+    pub(crate) fn get_animated_value(&self, property: css::Property) -> Option<css::Value> {
+        self.node.animations.lock().unwrap().get_written(property)
+    }
+    pub(crate) fn tick_animations(&self, now: Duration) -> bool {
+        let mut res = self.node.animations.lock().unwrap().tick(&self.style, now);
+        for child in self.get_children() {
+            res |= child.tick_animations(now)
         }
         res
     }
+
+    pub(crate) fn drawing(&self) -> MutexGuard<'_, Vec<DrawOp>> {
+        self.node.drawing.lock().unwrap()
+    }
+    pub(crate) fn set_image_src(&self, src: &str) {
+        *self.node.image_src.lock().unwrap() = Some(src.to_string());
+        self.mark_style_dirty()
+    }
+    pub(crate) fn take_image_srcs(&self, res: &mut Vec<(Element, String)>) {
+        if let Some(src) = self.node.image_src.lock().unwrap().take() {
+            res.push((self.clone(), src))
+        }
+        for child in self.get_children() {
+            child.take_image_srcs(res)
+        }
+    }
+
+    pub fn set_inner_html(&self, source: &str) {
+        self.remove_children();
+        self.node.text.lock().unwrap().clear();
+        html::parse_into(self, source)
+    }
+
+    pub fn get_border_radius(&self, ws: WindowSize) -> f32 {
+        match self.style.border_radius.get() {
+            CoordValue::Px(v) => v,
+            CoordValue::Perc(v) => v * self.get_width(ws).min(self.get_height(ws)),
+            CoordValue::Auto => 0.
+        }
+    }
+
+    fn get_display_list_loop(&self, ws: WindowSize, list: &mut DisplayList, opacity: f32, parent: &Matrix) {
+        let opacity = opacity * self.style.opacity.get();
+        let world = parent.multiply(&self.get_transform_matrix(ws));
+        let inverse = world.inverse();
+        let visible = inverse.is_some() && opacity > 0.;
+        let inv = inverse.unwrap_or_default();
+        let b = self.get_rect(ws);
+        let radius = self.get_border_radius(ws);
+        let record = |op: f32, r: Rect, c: Color, corner: f32, blur: f32| [
+            op, r.x0, r.x1, r.y0, r.y1,
+            c.r, c.g, c.b, c.a * opacity,
+            inv.a, inv.b, inv.c, inv.d, inv.e, inv.f,
+            corner.max(0.), blur,
+            b.x0, b.x1, b.y0, b.y1, radius, 0.
+        ];
+        let shadow_rect = |s: &Shadow, spread: f32| Rect::new(b.x0 + s.x - spread, b.y0 + s.y - spread, b.x1 + s.x + spread, b.y1 + s.y + spread);
+        list.owner = self.id();
+
+        let backdrop = self.style.backdrop_filter.get_blur();
+        if visible && backdrop > 0. {
+            list.command(RenderCommand::Backdrop { blur: backdrop });
+            list.push(record(OP_BACKDROP, b, Color::WHITE, radius, 0.))
+        }
+        let blur = self.style.filter.get_blur();
+        if blur > 0. { list.command(RenderCommand::PushLayer) }
+
+        let shadows = if visible { self.style.box_shadow.get() } else { Vec::new() };
+        for s in shadows.iter().rev().filter(|s| !s.inset) {
+            list.push(record(OP_SHADOW, shadow_rect(s, s.spread), s.color, radius + s.spread, s.blur / 2.))
+        }
+        let background = self.style.background.get();
+        match &background {
+            BackgroundType::Solid(c) if visible && c.a > 0. => list.push(record(OP_RECT, b, *c, radius, 0.)),
+            _ => list.push([0.; shader::RECORD_SIZE])
+        }
+        if let BackgroundType::Svg(svg) = &background {
+            let matrix = world.multiply(&Matrix::translate(b.x0, b.y0)).multiply(&svg.fit(b.x1 - b.x0, b.y1 - b.y0));
+            for op in svg.ops.iter().filter(|_| visible) {
+                let op_code = if op.is_even_odd() { OP_FILL_EVEN_ODD } else { OP_FILL_NON_ZERO };
+                list.push_path(op, &matrix, record(op_code, b, op.paint.color(), 0., 0.))
+            }
+        }
+        for s in shadows.iter().rev().filter(|s| s.inset) {
+            list.push(record(OP_INSET_SHADOW, shadow_rect(s, -s.spread), s.color, radius - s.spread, s.blur / 2.))
+        }
+        let drawing = if visible { self.drawing().clone() } else { Vec::new() };
+        let local = world.multiply(&Matrix::translate(b.x0, b.y0));
+        for op in drawing {
+            let op_code = if op.is_even_odd() { OP_FILL_EVEN_ODD } else { OP_FILL_NON_ZERO };
+            list.push_path(&op, &local, record(op_code, b, op.paint.color(), radius, 0.))
+        }
+
+        for child in self.get_children() {
+            child.get_display_list_loop(ws, list, opacity, &world)
+        }
+        list.owner = self.id();
+        if blur > 0. { list.command(RenderCommand::PopLayer { blur }) }
+    }
+    pub(crate) fn get_display_list(&self, ws: WindowSize) -> DisplayList {
+        let mut list = DisplayList::default();
+        self.get_display_list_loop(ws, &mut list, 1., &Matrix::IDENTITY);
+        list
+    }
+
+    pub fn get_content_width(&self) -> f32 {
+        // Children are copied out first, so the recursion into them does not hold this element's lock.
+        self.get_children().iter().map(|child| match child.style.width.get() {
+            CoordValue::Px(v) => v,
+            CoordValue::Perc(_) => 0.,
+            CoordValue::Auto => child.get_content_width()
+        }).sum()
+    }
+    pub fn get_content_height(&self) -> f32 {
+        self.get_children().iter().map(|child| match child.style.height.get() {
+            CoordValue::Px(v) => v,
+            CoordValue::Perc(_) => 0.,
+            CoordValue::Auto => child.get_content_height()
+        }).sum()
+    }
     pub fn get_width(&self, ws: WindowSize) -> f32 {
         match self.style.width.get() {
             CoordValue::Px(v) => v,
@@ -147,12 +641,48 @@ impl Element {
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub struct Style {
     pub width: Coord,
     pub height: Coord,
     pub x: Coord,
     pub y: Coord,
     pub background: Background,
-    pub position: Position
+    pub position: Position,
+    pub opacity: Opacity,
+    pub transform: Transform,
+    pub transform_origin: TransformOrigin,
+    pub border_radius: Coord,
+    pub box_shadow: BoxShadow,
+    pub filter: Filter,
+    pub backdrop_filter: Filter,
+    pub(crate) changes: Changes
+}
+impl Default for Style {
+    fn default() -> Self {
+        let style = Self {
+            width: Coord::default(),
+            height: Coord::default(),
+            x: Coord::default(),
+            y: Coord::default(),
+            background: Background::default(),
+            position: Position::default(),
+            opacity: Opacity::default(),
+            transform: Transform::default(),
+            transform_origin: TransformOrigin::default(),
+            border_radius: Coord::default(),
+            box_shadow: BoxShadow::default(),
+            filter: Filter::default(),
+            backdrop_filter: Filter::default(),
+            changes: Changes::default()
+        };
+        for changes in [
+            style.width.changes(), style.height.changes(), style.x.changes(), style.y.changes(), style.background.changes(),
+            style.position.changes(), style.opacity.changes(), style.transform.changes(), style.transform_origin.changes(),
+            style.border_radius.changes(), style.box_shadow.changes(), style.filter.changes(), style.backdrop_filter.changes()
+        ] {
+            changes.set_parent(Some(&style.changes))
+        }
+        style
+    }
 }
\ No newline at end of file
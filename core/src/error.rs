@@ -0,0 +1,80 @@
+use std::fmt;
+use wgpu::AdapterInfo;
+
+#[derive(Debug)]
+pub enum Error {
+    Window(winit::error::OsError),
+    CreateSurface(wgpu::CreateSurfaceError),
+    NoAdapter { available: Vec<AdapterInfo> },
+    IncompatibleSurface,
+    RequestDevice(wgpu::RequestDeviceError),
+    Surface(wgpu::SurfaceError),
+    MapBuffer(wgpu::BufferAsyncError),
+    InvalidSize { width: u32, height: u32 },
+    Image(image::ImageError)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Window(e) => write!(f, "Error creating window: {}", e),
+            Self::CreateSurface(e) => write!(f, "Error creating surface: {}", e),
+            Self::NoAdapter { available } => {
+                write!(f, "No adapter matches the configuration, available adapters:")?;
+                if available.is_empty() { return write!(f, " none") }
+                for info in available {
+                    write!(f, "\n  {} ({:?}, {:?})", info.name, info.device_type, info.backend)?
+                }
+                Ok(())
+            }
+            Self::IncompatibleSurface => write!(f, "The surface is not compatible with the adapter"),
+            Self::RequestDevice(e) => write!(f, "Error creating device: {}", e),
+            Self::Surface(e) => write!(f, "Error getting current surface texture: {}", e),
+            Self::MapBuffer(e) => write!(f, "Error reading back buffer: {}", e),
+            Self::InvalidSize { width, height } => write!(f, "Invalid image size {}x{}", width, height),
+            Self::Image(e) => write!(f, "Error saving image: {}", e)
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Window(e) => Some(e),
+            Self::CreateSurface(e) => Some(e),
+            Self::RequestDevice(e) => Some(e),
+            Self::Surface(e) => Some(e),
+            Self::MapBuffer(e) => Some(e),
+            Self::Image(e) => Some(e),
+            Self::NoAdapter { .. } | Self::IncompatibleSurface | Self::InvalidSize { .. } => None
+        }
+    }
+}
+impl From<winit::error::OsError> for Error {
+    fn from(e: winit::error::OsError) -> Self {
+        Self::Window(e)
+    }
+}
+impl From<wgpu::CreateSurfaceError> for Error {
+    fn from(e: wgpu::CreateSurfaceError) -> Self {
+        Self::CreateSurface(e)
+    }
+}
+impl From<wgpu::RequestDeviceError> for Error {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        Self::RequestDevice(e)
+    }
+}
+impl From<wgpu::SurfaceError> for Error {
+    fn from(e: wgpu::SurfaceError) -> Self {
+        Self::Surface(e)
+    }
+}
+impl From<wgpu::BufferAsyncError> for Error {
+    fn from(e: wgpu::BufferAsyncError) -> Self {
+        Self::MapBuffer(e)
+    }
+}
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Self::Image(e)
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
+pub use winit::event::MouseButton;
+
+use crate::{Context, Element};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EventType {
+    Click,
+    MouseDown,
+    MouseUp,
+    MouseMove
+}
+
+pub type EventListener = Arc<dyn Fn(Context, &ElementEvent) + Send + Sync>;
+
+pub struct ElementEvent {
+    pub kind: EventType,
+    pub target: Element,
+    pub x: f32,
+    pub y: f32,
+    pub button: Option<MouseButton>,
+    propagation_stopped: AtomicBool
+}
+impl ElementEvent {
+    pub(crate) fn new(kind: EventType, target: Element, (x, y): (f32, f32), button: Option<MouseButton>) -> Self {
+        Self { kind, target, x, y, button, propagation_stopped: AtomicBool::new(false) }
+    }
+    pub fn stop_propagation(&self) {
+        self.propagation_stopped.store(true, Ordering::Relaxed)
+    }
+    pub fn is_propagation_stopped(&self) -> bool {
+        self.propagation_stopped.load(Ordering::Relaxed)
+    }
+
+    // Returns whether any listener was called.
+    pub(crate) fn dispatch(&self, c: &Context) -> bool {
+        let mut called = false;
+        let mut current = Some(self.target.clone());
+        while let Some(e) = current {
+            for listener in e.get_event_listeners(self.kind) {
+                listener(c.clone(), self);
+                called = true
+            }
+            if self.is_propagation_stopped() { break }
+            current = e.get_parent()
+        }
+        called
+    }
+}
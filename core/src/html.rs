@@ -0,0 +1,405 @@
+use crate::Element;
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr"
+];
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+const CLOSES_P: &[&str] = &[
+    "address", "article", "aside", "blockquote", "div", "dl", "fieldset", "footer", "form",
+    "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "main", "nav", "ol", "p", "pre",
+    "section", "table", "ul"
+];
+const HEADINGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+
+#[derive(Debug)]
+enum Token {
+    StartTag { name: String, attributes: Vec<(String, String)> },
+    EndTag(String),
+    Text(String)
+}
+
+struct Tokenizer<'a> {
+    src: &'a str,
+    pos: usize,
+    raw_text: Option<String>
+}
+impl<'a> Tokenizer<'a> {
+    fn new(src: &'a str) -> Self {
+        Self { src, pos: 0, raw_text: None }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.bump();
+        }
+    }
+    fn skip_past(&mut self, pattern: &str) {
+        self.pos = match self.rest().find(pattern) {
+            Some(i) => self.pos + i + pattern.len(),
+            None => self.src.len()
+        }
+    }
+    fn read_name(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| !c.is_ascii_whitespace() && !matches!(c, '/' | '>' | '=')) {
+            self.bump();
+        }
+        self.src[start..self.pos].to_ascii_lowercase()
+    }
+
+    fn read_raw_text(&mut self, tag: &str) -> Token {
+        let rest = self.rest();
+        let lower = rest.to_ascii_lowercase();
+        let end = lower.find(&format!("</{tag}")).unwrap_or(rest.len());
+        self.pos += end;
+        Token::Text(rest[..end].to_string())
+    }
+
+    fn read_text(&mut self) -> Token {
+        let start = self.pos;
+        self.bump();
+        while let Some(c) = self.peek() {
+            if c == '<' && self.rest()[1..].starts_with(|c: char| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?')) {
+                break
+            }
+            self.bump();
+        }
+        Token::Text(decode_entities(&self.src[start..self.pos]))
+    }
+
+    fn read_attribute_value(&mut self) -> String {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.bump();
+                let start = self.pos;
+                let end = self.rest().find(quote).map_or(self.src.len(), |i| self.pos + i);
+                self.pos = (end + 1).min(self.src.len());
+                decode_entities(&self.src[start..end])
+            }
+            _ => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| !c.is_ascii_whitespace() && c != '>') {
+                    self.bump();
+                }
+                decode_entities(&self.src[start..self.pos])
+            }
+        }
+    }
+
+    fn read_tag(&mut self) -> Option<Token> {
+        self.bump();
+        if self.rest().starts_with("!--") {
+            self.skip_past("-->");
+            return None
+        }
+        if self.rest().starts_with(['!', '?']) {
+            self.skip_past(">");
+            return None
+        }
+        if self.peek() == Some('/') {
+            self.bump();
+            let name = self.read_name();
+            self.skip_past(">");
+            return if name.is_empty() { None } else { Some(Token::EndTag(name)) }
+        }
+        let name = self.read_name();
+        let mut attributes: Vec<(String, String)> = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => break,
+                Some('>') => { self.bump(); break }
+                Some('/') | Some('=') => { self.bump(); }
+                Some(_) => {
+                    let attr = self.read_name();
+                    self.skip_whitespace();
+                    let value = if self.peek() == Some('=') {
+                        self.bump();
+                        self.read_attribute_value()
+                    } else {
+                        String::new()
+                    };
+                    if !attributes.iter().any(|(n, _)| *n == attr) {
+                        attributes.push((attr, value))
+                    }
+                }
+            }
+        }
+        if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+            self.raw_text = Some(name.clone())
+        }
+        Some(Token::StartTag { name, attributes })
+    }
+}
+impl Iterator for Tokenizer<'_> {
+    type Item = Token;
+    fn next(&mut self) -> Option<Token> {
+        loop {
+            if let Some(tag) = self.raw_text.take() {
+                let token = self.read_raw_text(&tag);
+                if matches!(&token, Token::Text(v) if !v.is_empty()) { return Some(token) }
+            }
+            self.peek()?;
+            let is_tag = self.rest().starts_with('<') && self.rest()[1..].starts_with(|c: char| {
+                c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?')
+            });
+            if !is_tag { return Some(self.read_text()) }
+            if let Some(token) = self.read_tag() { return Some(token) }
+        }
+    }
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(num) = name.strip_prefix('#') {
+        let code = match num.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => num.parse().ok()?
+        };
+        return Some(char::from_u32(code).unwrap_or('\u{FFFD}'))
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{A0}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "laquo" => '«',
+        "raquo" => '»',
+        _ => return None
+    })
+}
+
+fn decode_entities(src: &str) -> String {
+    let mut res = String::with_capacity(src.len());
+    let mut rest = src;
+    while let Some(i) = rest.find('&') {
+        res.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let decoded = rest[1..].find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end + 1])?, end + 2)));
+        match decoded {
+            Some((c, len)) => {
+                res.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                res.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+fn collapse_whitespace(text: &str, trim_start: bool) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut last_space = trim_start;
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            if !last_space { res.push(' ') }
+            last_space = true
+        } else {
+            res.push(c);
+            last_space = false
+        }
+    }
+    res
+}
+
+struct TreeBuilder {
+    stack: Vec<(String, Element)>
+}
+impl TreeBuilder {
+    fn current(&self) -> &Element {
+        &self.stack.last().unwrap().1
+    }
+    fn is_open(&self, tag: &str) -> bool {
+        self.stack.iter().skip(1).any(|(t, _)| t == tag)
+    }
+    fn close(&mut self, tag: &str) -> bool {
+        match self.stack.iter().skip(1).rposition(|(t, _)| t == tag) {
+            Some(i) => {
+                self.stack.truncate(i + 1);
+                true
+            }
+            None => false
+        }
+    }
+    fn close_until(&mut self, tag: &str, boundaries: &[&str]) {
+        for (t, _) in self.stack.iter().skip(1).rev() {
+            if t == tag { break }
+            if boundaries.contains(&t.as_str()) { return }
+        }
+        self.close(tag);
+    }
+
+    fn start_tag(&mut self, name: String, attributes: Vec<(String, String)>) {
+        if CLOSES_P.contains(&name.as_str()) {
+            self.close_until("p", &["button", "li", "td", "th"]);
+        }
+        if HEADINGS.contains(&name.as_str()) {
+            if let Some((t, _)) = self.stack.last() {
+                if HEADINGS.contains(&t.as_str()) { self.stack.pop(); }
+            }
+        }
+        match name.as_str() {
+            "li" => self.close_until("li", &["ul", "ol"]),
+            "a" | "button" if self.is_open(&name) => { self.close(&name); }
+            _ => {}
+        }
+        let element = self.current().create_child();
+        element.set_tag(&name);
+        for (name, value) in attributes {
+            element.set_attribute(&name, &value)
+        }
+        if name == "img" {
+            let src = element.get_attribute("src").filter(|src| src.to_ascii_lowercase().ends_with(".svg"));
+            if let Some(src) = src { element.load_image(&src) }
+        }
+        if !VOID_ELEMENTS.contains(&name.as_str()) {
+            self.stack.push((name, element))
+        }
+    }
+
+    fn end_tag(&mut self, name: String) {
+        if !self.close(&name) && matches!(name.as_str(), "p" | "br") {
+            self.current().create_child().set_tag(&name)
+        }
+    }
+
+    fn text(&mut self, text: String) {
+        let (tag, element) = self.stack.last().unwrap();
+        if RAW_TEXT_ELEMENTS.contains(&tag.as_str()) {
+            return element.push_text(&text)
+        }
+        // Leading whitespace is dropped at the start of an element and after a run ending with a space.
+        let trim_start = match element.get_last_child() {
+            Some(last) if last.is_text() => last.get_text().ends_with(' '),
+            Some(_) => false,
+            None => true
+        };
+        let text = collapse_whitespace(&text, trim_start);
+        if !text.is_empty() {
+            element.push_text(&text)
+        }
+    }
+}
+
+pub fn parse_into(parent: &Element, source: &str) {
+    let mut builder = TreeBuilder { stack: vec![(String::new(), parent.clone())] };
+    for token in Tokenizer::new(source) {
+        match token {
+            Token::StartTag { name, attributes } => builder.start_tag(name, attributes),
+            Token::EndTag(name) => builder.end_tag(name),
+            Token::Text(text) => builder.text(text)
+        }
+    }
+}
+
+pub fn parse(source: &str) -> Element {
+    let root = Element::default();
+    parse_into(&root, source);
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump(element: &Element) -> String {
+        element.get_children().iter().map(|child| {
+            if child.is_text() { return format!("{:?}", child.get_text()) }
+            let mut res = child.get_tag();
+            if !child.get_children().is_empty() { res += &format!("({})", dump(child)) }
+            res
+        }).collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn tree_building() {
+        let cases = [
+            ("<p>a<p>b", r#"p("a") p("b")"#),
+            ("<p>a<div>b</div>", r#"p("a") div("b")"#),
+            ("<ul><li>a<li>b</ul>", r#"ul(li("a") li("b"))"#),
+            ("<ul><li>a<ul><li>b</ul><li>c</ul>", r#"ul(li("a" ul(li("b"))) li("c"))"#),
+            ("<h1>a<h2>b", r#"h1("a") h2("b")"#),
+            ("<a>1<a>2", r#"a("1") a("2")"#),
+            ("<div>a<br>b<img src=x>c</div>", r#"div("a" br "b" img "c")"#),
+            ("<p>Hello <span>x</span>!</p>", r#"p("Hello " span("x") "!")"#),
+            ("<p>a <!-- x --> b</p>", r#"p("a b")"#),
+            ("<div></p></div>", "div(p)"),
+            ("<div><span>x", r#"div(span("x"))"#),
+            ("<!-- <p>no</p> --><!DOCTYPE html><p>a</p>", r#"p("a")"#),
+            ("<P CLASS=x>a</p>", r#"p("a")"#)
+        ];
+        for (source, expected) in cases {
+            assert_eq!(dump(&parse(source)), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn text() {
+        let cases = [
+            ("<p>  a \n b  </p>", "a b "),
+            ("<p>a < b</p>", "a < b"),
+            ("<p>&lt;a&gt; &amp; &#65;&#x42; &bogus; & &nbsp;</p>", "<a> & AB &bogus; & \u{A0}"),
+            ("<script>if (a<b && c) {}</p></script>", "if (a<b && c) {}</p>"),
+            ("<style>p > a { color: red }</STYLE>", "p > a { color: red }"),
+            ("<textarea>&lt;raw&gt;</textarea>", "&lt;raw&gt;")
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse(source).get_children()[0].get_text_content(), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn content_size() {
+        let div = parse("<div><span></span><p><b></b></p></div>").get_children()[0].clone();
+        assert_eq!((div.get_content_width(), div.get_content_height()), (0., 0.));
+        let (span, p) = (div.get_children()[0].clone(), div.get_children()[1].clone());
+        span.style.width.set_px(10.);
+        let b = p.get_children()[0].clone();
+        b.style.width.set_px(5.);
+        b.style.height.set_px(4.);
+        assert_eq!((div.get_content_width(), div.get_content_height()), (15., 4.));
+    }
+
+    #[test]
+    fn image_src() {
+        let root = parse(r#"<img src="missing.svg"><img src="a.png">"#);
+        assert_eq!(root.get_children()[0].style.background.get(), crate::BackgroundType::None);
+        let mut pending = Vec::new();
+        root.take_image_srcs(&mut pending);
+        assert_eq!(pending.iter().map(|(_, src)| src.as_str()).collect::<Vec<_>>(), ["missing.svg"]);
+    }
+
+    #[test]
+    fn attributes() {
+        let element = parse(r#"<a href="x &amp; y" HREF=z data-v='1' checked/>"#).get_children()[0].clone();
+        assert_eq!(element.get_attribute("href").as_deref(), Some("x & y"));
+        assert_eq!(element.get_attribute("data-v").as_deref(), Some("1"));
+        assert_eq!(element.get_attribute("checked").as_deref(), Some(""));
+        assert_eq!(element.get_attributes().len(), 3);
+    }
+}
@@ -1,11 +1,24 @@
 pub use winit::event::VirtualKeyCode as Key;
 pub use winit::dpi::PhysicalSize;
+pub use winit::window::Icon;
+pub use wgpu::{Backends, PowerPreference, Features, Limits, AdapterInfo};
 
+mod error;      pub use error::*;
 mod app;        pub use app::*;
 mod context;    pub use context::*;
 mod container;  pub use container::*;
 mod script;     pub use script::*;
 mod element;    pub use element::*;
+mod event;      pub use event::*;
+mod view;       pub use view::*;
+mod reactive;   pub use reactive::*;
+mod animation;  pub use animation::*;
+mod canvas;     pub use canvas::*;
+mod svg;        pub use svg::*;
+mod task;       pub use task::*;
+mod time;       pub use time::*;
 mod utils;      pub use utils::*;
 
-pub mod shader;
\ No newline at end of file
+pub mod shader;
+pub mod html;
+pub mod css;
\ No newline at end of file
@@ -0,0 +1,294 @@
+use std::{sync::{Arc, Weak, Mutex, PoisonError, atomic::{AtomicBool, Ordering}}, cell::{RefCell, Cell}};
+
+type EffectFn = Box<dyn FnMut() + Send>;
+
+trait Source: Send + Sync {
+    fn unsubscribe(&self, effect: &Weak<EffectInner>);
+}
+
+struct EffectInner {
+    f: Mutex<EffectFn>,
+    running: AtomicBool,
+    rerun: AtomicBool,
+    sources: Mutex<Vec<Weak<dyn Source>>>
+}
+impl EffectInner {
+    fn run(self: &Arc<Self>) {
+        // An effect triggered by its own writes runs again once the current run returns.
+        if self.running.swap(true, Ordering::Relaxed) {
+            self.rerun.store(true, Ordering::Relaxed);
+            return
+        }
+        let _guard = RunGuard { effect: self, previous: CURRENT_EFFECT.with(|c| c.replace(Some(self.clone()))) };
+        for _ in 0..MAX_RERUNS {
+            // Dependencies are collected again on every run, so signals read only on a previous run stop triggering it.
+            let sources: Vec<_> = self.sources.lock().unwrap().drain(..).collect();
+            let effect = Arc::downgrade(self);
+            for source in sources.iter().filter_map(|s| s.upgrade()) {
+                source.unsubscribe(&effect)
+            }
+            (self.f.lock().unwrap_or_else(PoisonError::into_inner))();
+            if !self.rerun.swap(false, Ordering::Relaxed) { return }
+        }
+        log::warn!("Effect still triggers itself after {} runs, stopping", MAX_RERUNS)
+    }
+}
+
+const MAX_RERUNS: usize = 100;
+
+// Restores the current effect and clears the running flag even if the effect panics.
+struct RunGuard<'a> {
+    effect: &'a EffectInner,
+    previous: Option<Arc<EffectInner>>
+}
+impl Drop for RunGuard<'_> {
+    fn drop(&mut self) {
+        CURRENT_EFFECT.with(|c| c.replace(self.previous.take()));
+        self.effect.rerun.store(false, Ordering::Relaxed);
+        self.effect.running.store(false, Ordering::Relaxed)
+    }
+}
+
+thread_local! {
+    static CURRENT_EFFECT: RefCell<Option<Arc<EffectInner>>> = const { RefCell::new(None) };
+    static BATCH_DEPTH: Cell<usize> = const { Cell::new(0) };
+    static PENDING_EFFECTS: RefCell<Vec<Arc<EffectInner>>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn batch<R>(f: impl FnOnce() -> R) -> R {
+    BATCH_DEPTH.with(|d| d.set(d.get() + 1));
+    let res = f();
+    if BATCH_DEPTH.with(|d| { d.set(d.get() - 1); d.get() }) == 0 {
+        for effect in PENDING_EFFECTS.with(|p| p.take()) {
+            effect.run()
+        }
+    }
+    res
+}
+
+pub fn untrack<R>(f: impl FnOnce() -> R) -> R {
+    let previous = CURRENT_EFFECT.with(|c| c.replace(None));
+    let res = f();
+    CURRENT_EFFECT.with(|c| c.replace(previous));
+    res
+}
+
+pub struct Effect(Arc<EffectInner>);
+impl Effect {
+    pub fn new(f: impl FnMut() + Send + 'static) -> Self {
+        let inner = Arc::new(EffectInner { f: Mutex::new(Box::new(f)), running: AtomicBool::new(false), rerun: AtomicBool::new(false), sources: Default::default() });
+        inner.run();
+        Self(inner)
+    }
+    pub fn run(&self) {
+        self.0.run()
+    }
+}
+
+struct SignalInner<T> {
+    value: Mutex<T>,
+    subscribers: Mutex<Vec<Weak<EffectInner>>>
+}
+
+impl<T: Send> Source for SignalInner<T> {
+    fn unsubscribe(&self, effect: &Weak<EffectInner>) {
+        self.subscribers.lock().unwrap().retain(|s| !s.ptr_eq(effect))
+    }
+}
+
+pub struct Signal<T>(Arc<SignalInner<T>>);
+impl<T> Clone for Signal<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+impl<T: Clone + PartialEq + Send + 'static> Signal<T> {
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(SignalInner { value: Mutex::new(value), subscribers: Default::default() }))
+    }
+
+    fn track(&self) {
+        CURRENT_EFFECT.with(|c| {
+            if let Some(effect) = c.borrow().as_ref() {
+                let weak = Arc::downgrade(effect);
+                let added = {
+                    let mut subscribers = self.0.subscribers.lock().unwrap();
+                    let added = !subscribers.iter().any(|s| s.ptr_eq(&weak));
+                    if added { subscribers.push(weak) }
+                    added
+                };
+                if added {
+                    let source: Weak<dyn Source> = Arc::downgrade(&self.0) as _;
+                    effect.sources.lock().unwrap().push(source)
+                }
+            }
+        })
+    }
+    fn notify(&self) {
+        let effects: Vec<Arc<EffectInner>> = {
+            let mut subscribers = self.0.subscribers.lock().unwrap();
+            subscribers.retain(|s| s.strong_count() > 0);
+            subscribers.iter().filter_map(|s| s.upgrade()).collect()
+        };
+        for effect in effects {
+            if BATCH_DEPTH.with(|d| d.get()) > 0 {
+                PENDING_EFFECTS.with(|p| {
+                    let mut pending = p.borrow_mut();
+                    if !pending.iter().any(|e| Arc::ptr_eq(e, &effect)) { pending.push(effect) }
+                })
+            } else {
+                effect.run()
+            }
+        }
+    }
+
+    pub fn get(&self) -> T {
+        self.track();
+        self.get_untracked()
+    }
+    pub fn get_untracked(&self) -> T {
+        self.0.value.lock().unwrap().clone()
+    }
+    // The value stays locked while `f` runs, so `f` must not set this signal or it deadlocks.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        self.track();
+        f(&self.0.value.lock().unwrap())
+    }
+
+    pub fn set(&self, value: T) {
+        {
+            let mut current = self.0.value.lock().unwrap();
+            if *current == value { return }
+            *current = value
+        }
+        self.notify()
+    }
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        let mut value = self.get_untracked();
+        f(&mut value);
+        self.set(value)
+    }
+}
+
+pub struct Memo<T> {
+    signal: Signal<T>,
+    _effect: Arc<Effect>
+}
+impl<T> Clone for Memo<T> {
+    fn clone(&self) -> Self {
+        Self { signal: self.signal.clone(), _effect: self._effect.clone() }
+    }
+}
+impl<T: Clone + PartialEq + Send + 'static> Memo<T> {
+    pub fn new(f: impl Fn() -> T + Send + 'static) -> Self {
+        let slot: Arc<Mutex<Option<Signal<T>>>> = Default::default();
+        let effect = Effect::new({
+            let slot = slot.clone();
+            move || {
+                let value = f();
+                let signal = slot.lock().unwrap().clone();
+                match signal {
+                    Some(signal) => signal.set(value),
+                    None => *slot.lock().unwrap() = Some(Signal::new(value))
+                }
+            }
+        });
+        let signal = slot.lock().unwrap().clone().unwrap();
+        Self { signal, _effect: Arc::new(effect) }
+    }
+    pub fn get(&self) -> T {
+        self.signal.get()
+    }
+    pub fn get_untracked(&self) -> T {
+        self.signal.get_untracked()
+    }
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        self.signal.with(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dependencies_are_tracked_per_run() {
+        let (flag, a, b) = (Signal::new(true), Signal::new(0), Signal::new(0));
+        let runs = Arc::new(Mutex::new(0));
+        let _effect = Effect::new({
+            let (flag, a, b, runs) = (flag.clone(), a.clone(), b.clone(), runs.clone());
+            move || {
+                *runs.lock().unwrap() += 1;
+                if flag.get() { a.get(); } else { b.get(); }
+            }
+        });
+        let runs = || *runs.lock().unwrap();
+        assert_eq!(runs(), 1);
+        b.set(1);
+        assert_eq!(runs(), 1);
+        flag.set(false);
+        assert_eq!(runs(), 2);
+        a.set(1);
+        assert_eq!(runs(), 2);
+        b.set(2);
+        assert_eq!(runs(), 3);
+    }
+
+    #[test]
+    fn batch_and_memo() {
+        let (a, b) = (Signal::new(1), Signal::new(2));
+        let sum = Memo::new({
+            let (a, b) = (a.clone(), b.clone());
+            move || a.get() + b.get()
+        });
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let _effect = Effect::new({
+            let (sum, seen) = (sum.clone(), seen.clone());
+            move || seen.lock().unwrap().push(sum.get())
+        });
+        batch(|| { a.set(10); b.set(20) });
+        assert_eq!(sum.get_untracked(), 30);
+        assert_eq!(*seen.lock().unwrap().last().unwrap(), 30);
+        untrack(|| assert_eq!(sum.get(), 30));
+    }
+
+    #[test]
+    fn effect_triggered_by_itself() {
+        let a = Signal::new(0);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let _effect = Effect::new({
+            let (a, seen) = (a.clone(), seen.clone());
+            move || {
+                let v = a.get();
+                seen.lock().unwrap().push(v);
+                if v % 2 == 1 { a.set(v + 1) }
+            }
+        });
+        a.set(3);
+        assert_eq!(*seen.lock().unwrap(), [0, 3, 4]);
+
+        let counter = Signal::new(0);
+        let _effect = Effect::new({
+            let counter = counter.clone();
+            move || counter.set(counter.get() + 1)
+        });
+        assert_eq!(counter.get_untracked(), MAX_RERUNS as i32);
+    }
+
+    #[test]
+    fn panicking_effect() {
+        let fail = Signal::new(false);
+        let runs = Arc::new(Mutex::new(0));
+        let _effect = Effect::new({
+            let (fail, runs) = (fail.clone(), runs.clone());
+            move || {
+                *runs.lock().unwrap() += 1;
+                if fail.get() { panic!("effect failed") }
+            }
+        });
+        assert!(std::panic::catch_unwind(|| fail.set(true)).is_err());
+        assert!(CURRENT_EFFECT.with(|c| c.borrow().is_none()));
+        fail.set(false);
+        assert_eq!(*runs.lock().unwrap(), 3);
+    }
+}
@@ -1,10 +1,149 @@
+use std::{any::Any, path::PathBuf, sync::{Arc, Mutex, TryLockError, atomic::{AtomicU64, Ordering}}};
 use winit::dpi::PhysicalSize;
 
-use crate::Context;
+use crate::{Context, utils::Changes};
 
 #[allow(unused_variables)]
 pub trait Script {
-    fn setup(&self, _c: Context) {}
-    fn update(&self, _c: Context) {}
-    fn resize(&self, _c: Context, _new_size: PhysicalSize<u32>) {}
-}
\ No newline at end of file
+    fn setup(&mut self, _c: Context) {}
+    fn update(&mut self, _c: Context) {}
+    // Runs once per fixed timestep elapsed since the last frame. In `RedrawMode::OnDemand` frames only happen when
+    // something requests one, so keep calling `request_frame_in` with the timestep or use `RedrawMode::Continuous`.
+    fn fixed_update(&mut self, _c: Context) {}
+    fn resize(&mut self, _c: Context, _new_size: PhysicalSize<u32>) {}
+    // Returning false keeps the window open.
+    fn on_close_requested(&mut self, _c: Context) -> bool { true }
+    // Called when the window closes or the app exits.
+    fn on_exit(&mut self, _c: Context) {}
+    fn on_focus_changed(&mut self, _c: Context, _focused: bool) {}
+    fn on_suspended(&mut self, _c: Context) {}
+    fn on_resumed(&mut self, _c: Context) {}
+    fn on_scale_factor_changed(&mut self, _c: Context, _scale_factor: f64) {}
+    fn on_file_dropped(&mut self, _c: Context, _path: PathBuf) {}
+    // Events posted through an `EventSender`, downcast to the sent type.
+    fn on_user_event(&mut self, _c: Context, _event: &dyn Any) {}
+}
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) type SharedScript = Arc<Mutex<dyn Script>>;
+
+// A script calling back into one of its own methods would deadlock, so the nested call is skipped.
+pub(crate) fn with_script<R>(script: &SharedScript, f: impl FnOnce(&mut dyn Script) -> R) -> Option<R> {
+    match script.try_lock() {
+        Ok(mut script) => Some(f(&mut *script)),
+        Err(TryLockError::Poisoned(e)) => Some(f(&mut *e.into_inner())),
+        Err(TryLockError::WouldBlock) => {
+            log::warn!("Skipping a nested call into a running script");
+            None
+        }
+    }
+}
+
+pub(crate) struct ScriptEntry {
+    pub id: u64,
+    pub priority: i32,
+    pub enabled: bool,
+    pub needs_setup: bool,
+    pub script: SharedScript
+}
+
+// Entries are kept sorted by priority, scripts with a lower priority run first.
+// `changes` is marked when scripts are added, removed or toggled so the next frame picks them up.
+#[derive(Clone, Default)]
+pub(crate) struct Scripts {
+    entries: Arc<Mutex<Vec<ScriptEntry>>>,
+    pub changes: Changes
+}
+impl Scripts {
+    pub fn add(&self, script: impl Script + 'static, priority: i32) -> ScriptHandle {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let mut entries = self.entries.lock().unwrap();
+        entries.push(ScriptEntry { id, priority, enabled: true, needs_setup: true, script: Arc::new(Mutex::new(script)) });
+        entries.sort_by_key(|entry| entry.priority);
+        self.changes.mark();
+        ScriptHandle { id, scripts: self.clone() }
+    }
+    pub fn handles(&self) -> Vec<ScriptHandle> {
+        self.entries.lock().unwrap().iter().map(|entry| ScriptHandle { id: entry.id, scripts: self.clone() }).collect()
+    }
+    pub fn enabled(&self) -> Vec<SharedScript> {
+        self.entries.lock().unwrap().iter().filter(|entry| entry.enabled).map(|entry| entry.script.clone()).collect()
+    }
+    // Enabled scripts that have not been set up yet, marking them as set up.
+    pub fn take_pending_setup(&self) -> Vec<SharedScript> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.iter_mut().filter(|entry| entry.enabled && entry.needs_setup).map(|entry| {
+            entry.needs_setup = false;
+            entry.script.clone()
+        }).collect()
+    }
+}
+
+#[derive(Clone)]
+pub struct ScriptHandle {
+    id: u64,
+    scripts: Scripts
+}
+impl PartialEq for ScriptHandle {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+impl Eq for ScriptHandle {}
+impl ScriptHandle {
+    fn with_entry<R>(&self, f: impl FnOnce(&mut ScriptEntry) -> R) -> Option<R> {
+        self.scripts.entries.lock().unwrap().iter_mut().find(|entry| entry.id == self.id).map(f)
+    }
+    pub fn is_attached(&self) -> bool {
+        self.with_entry(|_| ()).is_some()
+    }
+    pub fn remove(&self) {
+        let mut entries = self.scripts.entries.lock().unwrap();
+        let len = entries.len();
+        entries.retain(|entry| entry.id != self.id);
+        if entries.len() != len {
+            self.scripts.changes.mark()
+        }
+    }
+    pub fn is_enabled(&self) -> bool {
+        self.with_entry(|entry| entry.enabled).unwrap_or(false)
+    }
+    // A script disabled before its setup is set up on the first frame after it is enabled.
+    pub fn set_enabled(&self, enabled: bool) {
+        if self.with_entry(|entry| std::mem::replace(&mut entry.enabled, enabled)) == Some(!enabled) {
+            self.scripts.changes.mark()
+        }
+    }
+    pub fn get_priority(&self) -> i32 {
+        self.with_entry(|entry| entry.priority).unwrap_or_default()
+    }
+    pub fn set_priority(&self, priority: i32) {
+        if self.with_entry(|entry| entry.priority = priority).is_some() {
+            self.scripts.entries.lock().unwrap().sort_by_key(|entry| entry.priority)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Empty;
+    impl Script for Empty {}
+
+    #[test]
+    fn changes() {
+        let scripts = Scripts::default();
+        let handle = scripts.add(Empty, 0);
+        assert_eq!(scripts.changes.version(), 1);
+        for (enabled, version) in [(true, 1), (false, 2), (false, 2), (true, 3)] {
+            handle.set_enabled(enabled);
+            assert_eq!(scripts.changes.version(), version);
+        }
+        handle.remove();
+        handle.remove();
+        handle.set_enabled(false);
+        assert_eq!(scripts.changes.version(), 4);
+        assert!(scripts.enabled().is_empty());
+    }
+}
@@ -0,0 +1,23 @@
+@vertex fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+    switch i32(i) {
+        case 0:  { return vec4<f32>(-1.,  1., 0., 1.); }
+        case 1:  { return vec4<f32>(-1., -1., 0., 1.); }
+        case 2:  { return vec4<f32>( 1., -1., 0., 1.); }
+        case 3:  { return vec4<f32>( 1.,  1., 0., 1.); }
+        case 4:  { return vec4<f32>(-1.,  1., 0., 1.); }
+        default: { return vec4<f32>( 1., -1., 0., 1.); }
+    }
+}
+
+@group(0) @binding(0)
+var canvas: texture_2d<f32>;
+
+@fragment fn fs_main(@builtin(position) pixel_pos: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(canvas, vec2<i32>(pixel_pos.xy), 0);
+}
+
+@fragment fn fs_unpremultiply(@builtin(position) pixel_pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(canvas, vec2<i32>(pixel_pos.xy), 0);
+    if color.a <= 0. { return vec4<f32>(0.); }
+    return vec4<f32>(color.rgb / color.a, color.a);
+}
@@ -0,0 +1,41 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) params: u32
+}
+
+// The instance index carries the pass parameters: bit 0 is the direction, the rest is sigma in 1/16 px.
+@vertex fn vs_main(@builtin(vertex_index) i: u32, @builtin(instance_index) params: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.params = params;
+    switch i32(i) {
+        case 0:  { out.position = vec4<f32>(-1.,  1., 0., 1.); }
+        case 1:  { out.position = vec4<f32>(-1., -1., 0., 1.); }
+        case 2:  { out.position = vec4<f32>( 1., -1., 0., 1.); }
+        case 3:  { out.position = vec4<f32>( 1.,  1., 0., 1.); }
+        case 4:  { out.position = vec4<f32>(-1.,  1., 0., 1.); }
+        default: { out.position = vec4<f32>( 1., -1., 0., 1.); }
+    }
+    return out;
+}
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let p = vec2<i32>(in.position.xy);
+    let sigma = f32(in.params >> 1u) / 16.;
+    if(sigma < 0.1) {
+        return textureLoad(source, p, 0);
+    }
+    let direction = select(vec2<i32>(1, 0), vec2<i32>(0, 1), (in.params & 1u) == 1u);
+    let size = vec2<i32>(textureDimensions(source)) - 1;
+    let radius = i32(ceil(sigma * 3.));
+    var sum = vec4<f32>(0.);
+    var weight = 0.;
+    for(var k = -radius; k <= radius; k++) {
+        let w = exp(-f32(k * k) / (2. * sigma * sigma));
+        sum += textureLoad(source, clamp(p + direction * k, vec2<i32>(0), size), 0) * w;
+        weight += w;
+    }
+    return sum / weight;
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use super::RECORD_SIZE;
+use crate::{DrawOp, Matrix, Rect};
+
+pub(crate) const OP_RECT: f32 = 1.;
+pub(crate) const OP_SHADOW: f32 = 2.;
+pub(crate) const OP_INSET_SHADOW: f32 = 3.;
+pub(crate) const OP_BACKDROP: f32 = 4.;
+pub(crate) const OP_BREAK: f32 = 5.;
+pub(crate) const OP_FILL_NON_ZERO: f32 = 6.;
+pub(crate) const OP_FILL_EVEN_ODD: f32 = 7.;
+
+// Fields of a record after the op, bounds, color and inverse matrix. Paths store their range in the path data
+// in place of the corner radius and blur.
+pub(crate) const RECORD_CORNER: usize = 15;
+pub(crate) const RECORD_BLUR: usize = 16;
+pub(crate) const RECORD_BOX: usize = 17;
+pub(crate) const RECORD_PAINT_LEN: usize = 22;
+pub(crate) const PATH_START: usize = RECORD_CORNER;
+pub(crate) const PATH_EDGES: usize = RECORD_BLUR;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum RenderCommand {
+    // Draws the records starting at this index until the next break.
+    Draw(u32),
+    PushLayer,
+    PopLayer { blur: f32 },
+    Backdrop { blur: f32 }
+}
+
+pub(crate) struct DisplayList {
+    pub data: Vec<f32>,
+    pub path_data: Vec<f32>,
+    pub commands: Vec<RenderCommand>,
+    // The element that pushed each record, used to diff display lists by element.
+    pub owners: Vec<usize>,
+    pub owner: usize
+}
+impl Default for DisplayList {
+    fn default() -> Self {
+        Self { data: Vec::new(), path_data: Vec::new(), commands: vec![RenderCommand::Draw(0)], owners: Vec::new(), owner: 0 }
+    }
+}
+impl DisplayList {
+    pub fn push(&mut self, record: [f32; RECORD_SIZE]) {
+        self.data.extend_from_slice(&record);
+        self.owners.push(self.owner)
+    }
+    // Pushes a path record whose edges and paint are stored in the path data, right after each other.
+    pub fn push_path(&mut self, op: &DrawOp, matrix: &Matrix, mut record: [f32; RECORD_SIZE]) {
+        let edges = op.edges(matrix);
+        let bounds = edges.iter().fold(Rect::default(), |r, e| {
+            r.union(&Rect::new(e[0].min(e[2]), e[1].min(e[3]), e[0].max(e[2]) + 1e-3, e[1].max(e[3]) + 1e-3))
+        });
+        if edges.is_empty() || bounds.is_empty() { return }
+        let paint = op.paint_data(matrix);
+        (record[1], record[2], record[3], record[4]) = (bounds.x0, bounds.x1, bounds.y0, bounds.y1);
+        (record[PATH_START], record[PATH_EDGES], record[RECORD_PAINT_LEN]) = ((self.path_data.len() / 4) as f32, edges.len() as f32, paint.len() as f32);
+        self.path_data.extend(edges.iter().chain(paint.iter()).flatten());
+        self.push(record)
+    }
+    pub fn command(&mut self, command: RenderCommand) {
+        let mut record = [0.; RECORD_SIZE];
+        record[0] = OP_BREAK;
+        self.push(record);
+        self.commands.push(command);
+        self.commands.push(RenderCommand::Draw((self.data.len() / RECORD_SIZE) as u32))
+    }
+    pub fn has_effects(&self) -> bool {
+        self.commands.len() > 1
+    }
+
+    fn records(&self) -> impl Iterator<Item = &[f32]> {
+        self.data.chunks_exact(RECORD_SIZE)
+    }
+
+    // Groups the records by the element that pushed them, along with the element drawn before it.
+    fn elements(&self) -> HashMap<usize, (Option<usize>, Vec<&[f32]>)> {
+        let mut res: HashMap<usize, (Option<usize>, Vec<&[f32]>)> = HashMap::new();
+        let mut previous = None;
+        for (owner, record) in self.owners.iter().zip(self.records()) {
+            res.entry(*owner).or_insert((previous, Vec::new())).1.push(record);
+            previous = Some(*owner)
+        }
+        res
+    }
+
+    // Returns the area to redraw when this list replaces `old`. An element is damaged when its records, its paths
+    // or the element drawn before it have changed, so records that only moved in the buffer are not redrawn.
+    pub fn damage(&self, old: &DisplayList) -> Rect {
+        let (old_elements, new_elements) = (old.elements(), self.elements());
+        let bounds = |records: &[&[f32]]| records.iter().fold(Rect::default(), |r, record| r.union(&record_bounds(record)));
+        let mut damage = Rect::default();
+        for (owner, (previous, records)) in new_elements.iter() {
+            match old_elements.get(owner) {
+                Some((old_previous, old_records)) if old_previous == previous && old_records.len() == records.len()
+                    && old_records.iter().zip(records.iter()).all(|(a, b)| same_record(&old.path_data, a, &self.path_data, b)) => {}
+                Some((_, old_records)) => damage = damage.union(&bounds(old_records)).union(&bounds(records)),
+                None => damage = damage.union(&bounds(records))
+            }
+        }
+        for (owner, (_, records)) in old_elements.iter() {
+            if !new_elements.contains_key(owner) { damage = damage.union(&bounds(records)) }
+        }
+        damage
+    }
+}
+
+fn is_path(record: &[f32]) -> bool {
+    record[0] == OP_FILL_NON_ZERO || record[0] == OP_FILL_EVEN_ODD
+}
+fn record_path_data<'a>(path_data: &'a [f32], record: &[f32]) -> &'a [f32] {
+    if !is_path(record) { return &[] }
+    let start = record[PATH_START] as usize * 4;
+    path_data.get(start..start + (record[PATH_EDGES] + record[RECORD_PAINT_LEN]) as usize * 4).unwrap_or_default()
+}
+// Paths are compared by their edges and paint rather than where they are stored in the path data.
+fn same_record(a_paths: &[f32], a: &[f32], b_paths: &[f32], b: &[f32]) -> bool {
+    if !is_path(a) || !is_path(b) { return a == b }
+    a[..PATH_START] == b[..PATH_START] && a[PATH_START + 1..] == b[PATH_START + 1..]
+        && record_path_data(a_paths, a) == record_path_data(b_paths, b)
+}
+
+fn record_bounds(record: &[f32]) -> Rect {
+    if record[0] == 0. || record[0] == OP_BREAK { return Rect::default() }
+    if is_path(record) {
+        return Rect::new(record[1].floor() - 1., record[3].floor() - 1., record[2].ceil() + 1., record[4].ceil() + 1.)
+    }
+    let inverse = Matrix::new(record[9], record[10], record[11], record[12], record[13], record[14]);
+    let matrix = match inverse.inverse() { Some(v) => v, None => return Rect::default() };
+    let blur = record[RECORD_BLUR] * 3.;
+    let b = &record[RECORD_BOX..RECORD_BOX + 4];
+    let rect = Rect::new(record[1] - blur, record[3] - blur, record[2] + blur, record[4] + blur)
+        .union(&Rect::new(b[0], b[2], b[1], b[3]));
+    [(rect.x0, rect.y0), (rect.x1, rect.y0), (rect.x0, rect.y1), (rect.x1, rect.y1)].iter()
+        .map(|(x, y)| matrix.apply(*x, *y))
+        .fold(None, |r: Option<Rect>, (x, y)| Some(match r {
+            Some(r) => Rect::new(r.x0.min(x), r.y0.min(y), r.x1.max(x), r.y1.max(y)),
+            None => Rect::new(x, y, x, y)
+        }))
+        .map(|r| Rect::new(r.x0.floor() - 1., r.y0.floor() - 1., r.x1.ceil() + 1., r.y1.ceil() + 1.))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x0: f32, y0: f32, x1: f32, y1: f32) -> [f32; RECORD_SIZE] {
+        [OP_RECT, x0, x1, y0, y1, 1., 1., 1., 1., 1., 0., 0., 1., 0., 0., 0., 0., x0, x1, y0, y1, 0., 0.]
+    }
+    fn path(list: &mut DisplayList, owner: usize, x0: f32, y0: f32, x1: f32, y1: f32) {
+        let mut record = rect(x0, y0, x1, y1);
+        record[0] = OP_FILL_NON_ZERO;
+        (record[PATH_START], record[PATH_EDGES], record[RECORD_PAINT_LEN]) = ((list.path_data.len() / 4) as f32, 1., 0.);
+        list.path_data.extend([x0, y0, x1, y1]);
+        list.owner = owner;
+        list.push(record)
+    }
+    fn list(records: &[(usize, [f32; RECORD_SIZE])]) -> DisplayList {
+        let mut list = DisplayList::default();
+        for (owner, record) in records {
+            list.owner = *owner;
+            list.push(*record)
+        }
+        list
+    }
+
+    #[test]
+    fn bounds() {
+        let mut blurred = rect(10., 20., 30., 40.);
+        blurred[RECORD_BLUR] = 2.;
+        let mut translated = rect(10., 20., 30., 40.);
+        translated[13] = -5.;
+        let mut break_record = [0.; RECORD_SIZE];
+        break_record[0] = OP_BREAK;
+        let mut path_list = DisplayList::default();
+        path(&mut path_list, 1, 10.5, 20.5, 30.5, 40.5);
+        let cases = [
+            ([0.; RECORD_SIZE], Rect::default()),
+            (break_record, Rect::default()),
+            (rect(10., 20., 30., 40.), Rect::new(9., 19., 31., 41.)),
+            (blurred, Rect::new(3., 13., 37., 47.)),
+            (translated, Rect::new(14., 19., 36., 41.)),
+            (path_list.data.as_slice().try_into().unwrap(), Rect::new(9., 19., 32., 42.))
+        ];
+        for (record, expected) in cases {
+            assert_eq!(record_bounds(&record), expected, "{record:?}");
+        }
+    }
+
+    #[test]
+    fn damage() {
+        let (a, b, c, x) = (rect(0., 0., 10., 10.), rect(20., 0., 30., 10.), rect(40., 0., 50., 10.), rect(60., 0., 70., 10.));
+        let old = list(&[(1, a), (2, b), (3, c)]);
+        let mut changed = b;
+        changed[5] = 0.;
+        let cases = [
+            (list(&[(1, a), (2, b), (3, c)]), Rect::default()),
+            (list(&[(1, a), (2, changed), (3, c)]), Rect::new(19., -1., 31., 11.)),
+            (list(&[(1, a), (3, c)]), Rect::new(19., -1., 51., 11.)),
+            (list(&[(1, a), (2, b), (3, c), (4, x)]), Rect::new(59., -1., 71., 11.)),
+            (list(&[(1, a), (4, x), (2, b), (3, c)]), Rect::new(19., -1., 71., 11.)),
+            (list(&[(1, a), (3, c), (2, b)]), Rect::new(19., -1., 51., 11.))
+        ];
+        for (new, expected) in cases {
+            assert_eq!(new.damage(&old), expected, "{:?}", new.owners);
+        }
+    }
+
+    #[test]
+    fn moved_paths() {
+        let mut old = DisplayList::default();
+        path(&mut old, 1, 0., 0., 10., 10.);
+        path(&mut old, 2, 20., 0., 30., 10.);
+        let mut new = list(&[(1, rect(0., 0., 10., 10.))]);
+        path(&mut new, 2, 20., 0., 30., 10.);
+        assert_eq!(new.damage(&old), Rect::new(-1., -1., 11., 11.));
+        let mut moved = list(&[(1, rect(0., 0., 10., 10.))]);
+        path(&mut moved, 2, 20., 0., 30., 20.);
+        assert_eq!(moved.damage(&new), Rect::new(19., -1., 31., 21.));
+    }
+}
@@ -1,13 +1,102 @@
-use wgpu::{Device, RenderPipeline, TextureFormat};
+use std::sync::Arc;
+use wgpu::{Device, RenderPipeline, TextureFormat, BindGroupLayout, BlendState};
 
-pub fn new(device: &Device, surface_format: TextureFormat) -> RenderPipeline {
+mod display_list; pub(crate) use display_list::*;
+
+pub const RECORD_SIZE: usize = 23;
+
+pub struct Pipelines {
+    pub main: RenderPipeline,
+    pub clear: RenderPipeline,
+    pub blit: RenderPipeline,
+    pub unpremultiply: RenderPipeline,
+    pub blur: RenderPipeline,
+    pub blur_blend: RenderPipeline,
+    pub data_layout: Arc<BindGroupLayout>,
+    pub texture_layout: Arc<BindGroupLayout>,
+    pub format: TextureFormat
+}
+impl Pipelines {
+    // Rebuilt pipelines share the bind group layouts, so existing bind groups stay valid.
+    pub fn rebuild(&self, device: &Device, surface_format: TextureFormat) -> Self {
+        create_pipelines(device, surface_format, self.data_layout.clone(), self.texture_layout.clone())
+    }
+}
+
+pub fn new(device: &Device, surface_format: TextureFormat) -> Pipelines {
+    let storage = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None
+        },
+        count: None
+    };
+    let data_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[storage(0), storage(1)]
+    }).into();
+    let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false
+            },
+            count: None
+        }]
+    }).into();
+    create_pipelines(device, surface_format, data_layout, texture_layout)
+}
+
+fn create_pipelines(
+    device: &Device,
+    surface_format: TextureFormat,
+    data_layout: Arc<BindGroupLayout>,
+    texture_layout: Arc<BindGroupLayout>
+) -> Pipelines {
+    let shader = include_str!("shader.wgsl");
+    let blit = include_str!("blit.wgsl");
+    let blur = include_str!("blur.wgsl");
+    let premultiplied = Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING);
+    Pipelines {
+        main: create_pipeline(device, surface_format, shader, "fs_main", &[&data_layout, &texture_layout], premultiplied),
+        clear: create_pipeline(device, surface_format, shader, "fs_clear", &[], None),
+        blit: create_pipeline(device, surface_format, blit, "fs_main", &[&texture_layout], None),
+        unpremultiply: create_pipeline(device, surface_format, blit, "fs_unpremultiply", &[&texture_layout], None),
+        blur: create_pipeline(device, surface_format, blur, "fs_main", &[&texture_layout], None),
+        blur_blend: create_pipeline(device, surface_format, blur, "fs_main", &[&texture_layout], premultiplied),
+        data_layout,
+        texture_layout,
+        format: surface_format
+    }
+}
+
+fn create_pipeline(
+    device: &Device,
+    surface_format: TextureFormat,
+    source: &'static str,
+    fragment_entry: &str,
+    bind_group_layouts: &[&BindGroupLayout],
+    blend: Option<BlendState>
+) -> RenderPipeline {
     let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
         label: None,
-        source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!("shader.wgsl"))),
+        source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(source)),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts,
+        push_constant_ranges: &[]
     });
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: None,
-        layout: None,
+        layout: Some(&layout),
         vertex: wgpu::VertexState {
             module: &shader,
             entry_point: "vs_main",
@@ -15,11 +104,11 @@ pub fn new(device: &Device, surface_format: TextureFormat) -> RenderPipeline {
         },
         fragment: Some(wgpu::FragmentState {
             module: &shader,
-            entry_point: "fs_main",
+            entry_point: fragment_entry,
             targets: &[Some(wgpu::ColorTargetState {
                 format: surface_format,
-                blend: None,
-                write_mask: wgpu::ColorWrites::COLOR
+                blend,
+                write_mask: wgpu::ColorWrites::ALL
             })]
         }),
         primitive: wgpu::PrimitiveState {
@@ -32,6 +121,8 @@ pub fn new(device: &Device, surface_format: TextureFormat) -> RenderPipeline {
             conservative: false
         },
         depth_stencil: None,
+        // Everything is drawn by a full-screen quad whose fragment shader computes the exact coverage of each
+        // pixel, so anti-aliasing is analytic and multisampling would only repeat the same result per sample.
         multisample: wgpu::MultisampleState {
             count: 1,
             mask: !0,
@@ -39,4 +130,4 @@ pub fn new(device: &Device, surface_format: TextureFormat) -> RenderPipeline {
         },
         multiview: None
     })
-}
\ No newline at end of file
+}
@@ -1,48 +1,254 @@
-@vertex fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) start: u32
+}
+
+@vertex fn vs_main(@builtin(vertex_index) i: u32, @builtin(instance_index) start: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.start = start;
     switch i32(i) {
-        case 0:  { return vec4<f32>(-1.,  1., 0., 1.); }
-        case 1:  { return vec4<f32>(-1., -1., 0., 1.); }
-        case 2:  { return vec4<f32>( 1., -1., 0., 1.); }
-        case 3:  { return vec4<f32>( 1.,  1., 0., 1.); }
-        case 4:  { return vec4<f32>(-1.,  1., 0., 1.); }
-        default: { return vec4<f32>( 1., -1., 0., 1.); }
+        case 0:  { out.position = vec4<f32>(-1.,  1., 0., 1.); }
+        case 1:  { out.position = vec4<f32>(-1., -1., 0., 1.); }
+        case 2:  { out.position = vec4<f32>( 1., -1., 0., 1.); }
+        case 3:  { out.position = vec4<f32>( 1.,  1., 0., 1.); }
+        case 4:  { out.position = vec4<f32>(-1.,  1., 0., 1.); }
+        default: { out.position = vec4<f32>( 1., -1., 0., 1.); }
     }
+    return out;
 }
 
 @group(0) @binding(0)
 var<storage, read> data: array<f32>;
 
+@group(0) @binding(1)
+var<storage, read> path_data: array<vec4<f32>>;
+
+@group(1) @binding(0)
+var backdrop: texture_2d<f32>;
+
+const RECORD_SIZE: i32 = 23;
+
 var<private> i: i32;
 var<private> color: vec4<f32>;
-var<private> px: f32;
-var<private> py: f32;
+var<private> pixel: vec2<f32>;
+
+fn record_color() -> vec4<f32> {
+    return vec4<f32>(data[i + 5], data[i + 6], data[i + 7], data[i + 8]);
+}
 
-fn read_f32() -> f32 {
-    var res = data[i];
-    i += 1;
-    return res;
+fn local_point() -> vec2<f32> {
+    return vec2<f32>(
+        data[i + 9] * pixel.x + data[i + 11] * pixel.y + data[i + 13],
+        data[i + 10] * pixel.x + data[i + 12] * pixel.y + data[i + 14]
+    );
+}
+
+fn blend(c: vec4<f32>, coverage: f32) {
+    let a = c.a * coverage;
+    color = vec4<f32>(c.rgb * a + color.rgb * (1. - a), a + color.a * (1. - a));
+}
+
+fn rounded_rect_coverage(p: vec2<f32>, lower: vec2<f32>, upper: vec2<f32>, radius: f32) -> f32 {
+    let half_size = (upper - lower) * 0.5;
+    let r = clamp(radius, 0., min(half_size.x, half_size.y));
+    let q = abs(p - (lower + upper) * 0.5) - half_size + r;
+    let d = length(max(q, vec2<f32>(0.))) + min(max(q.x, q.y), 0.) - r;
+    return clamp(0.5 - d, 0., 1.);
+}
+
+fn gaussian(x: f32, sigma: f32) -> f32 {
+    return exp(-(x * x) / (2. * sigma * sigma)) / (2.506628 * sigma);
+}
+
+fn erf(v: vec2<f32>) -> vec2<f32> {
+    let s = sign(v);
+    let a = abs(v);
+    var x = 1. + (0.278393 + (0.230389 + 0.078108 * (a * a)) * a) * a;
+    x *= x;
+    return s - s / (x * x);
+}
+
+fn rounded_box_shadow_x(x: f32, y: f32, sigma: f32, corner: f32, half_size: vec2<f32>) -> f32 {
+    let delta = min(half_size.y - corner - abs(y), 0.);
+    let curved = half_size.x - corner + sqrt(max(0., corner * corner - delta * delta));
+    let integral = 0.5 + 0.5 * erf((x + vec2<f32>(-curved, curved)) * (0.7071068 / sigma));
+    return integral.y - integral.x;
+}
+
+// Coverage of a rounded rectangle convolved with a gaussian, integrated analytically along x and sampled along y.
+fn rounded_box_shadow(p: vec2<f32>, lower: vec2<f32>, upper: vec2<f32>, sigma: f32, radius: f32) -> f32 {
+    if(sigma < 0.25) {
+        return rounded_rect_coverage(p, lower, upper, radius);
+    }
+    let half_size = (upper - lower) * 0.5;
+    let corner = clamp(radius, 0., min(half_size.x, half_size.y));
+    let point = p - (lower + upper) * 0.5;
+    let low = point.y - half_size.y;
+    let high = point.y + half_size.y;
+    let start = clamp(-3. * sigma, low, high);
+    let end = clamp(3. * sigma, low, high);
+    let step = (end - start) / 4.;
+    var y = start + step * 0.5;
+    var value = 0.;
+    for(var k = 0; k < 4; k++) {
+        value += rounded_box_shadow_x(point.x, point.y - y, sigma, corner, half_size) * gaussian(y, sigma) * step;
+        y += step;
+    }
+    return value;
+}
+
+fn rect_lower() -> vec2<f32> {
+    return vec2<f32>(data[i + 1], data[i + 3]);
+}
+fn rect_upper() -> vec2<f32> {
+    return vec2<f32>(data[i + 2], data[i + 4]);
+}
+fn box_coverage(p: vec2<f32>) -> f32 {
+    return rounded_rect_coverage(p, vec2<f32>(data[i + 17], data[i + 19]), vec2<f32>(data[i + 18], data[i + 20]), data[i + 21]);
 }
 
 fn draw_rect() {
-    if(px < data[i] || px > data[i + 1] || py < data[i + 2] || py > data[i + 3]) {
-        i += 8;
+    blend(record_color(), rounded_rect_coverage(local_point(), rect_lower(), rect_upper(), data[i + 15]));
+}
+
+fn draw_shadow() {
+    let p = local_point();
+    let shadow = rounded_box_shadow(p, rect_lower(), rect_upper(), data[i + 16], data[i + 15]);
+    blend(record_color(), shadow * (1. - box_coverage(p)));
+}
+
+fn draw_inset_shadow() {
+    let p = local_point();
+    let inner = rounded_box_shadow(p, rect_lower(), rect_upper(), data[i + 16], data[i + 15]);
+    blend(record_color(), (1. - inner) * box_coverage(p));
+}
+
+fn draw_backdrop() {
+    let coverage = rounded_rect_coverage(local_point(), rect_lower(), rect_upper(), data[i + 15]);
+    let c = textureLoad(backdrop, vec2<i32>(pixel), 0) * coverage * data[i + 8];
+    color = c + color * (1. - c.a);
+}
+
+fn ramp(u: f32) -> f32 {
+    if(u <= 0.) { return 0.; }
+    if(u < 1.) { return u * u * 0.5; }
+    return u - 0.5;
+}
+
+// Signed area of the pixel square starting at `lower` that lies left of the edge, i.e. its winding contribution integrated over the pixel.
+fn edge_area(e: vec4<f32>, lower: vec2<f32>) -> f32 {
+    let y0 = clamp(e.y, lower.y, lower.y + 1.);
+    let y1 = clamp(e.w, lower.y, lower.y + 1.);
+    if(y0 == y1) { return 0.; }
+    let slope = (e.z - e.x) / (e.w - e.y);
+    let x0 = e.x + (y0 - e.y) * slope - lower.x;
+    let x1 = e.x + (y1 - e.y) * slope - lower.x;
+    var mean = clamp((x0 + x1) * 0.5, 0., 1.);
+    if(abs(x1 - x0) > 1e-4) {
+        mean = (ramp(x1) - ramp(x0)) / (x1 - x0);
+    }
+    return (y1 - y0) * mean;
+}
+
+fn edge_distance(e: vec4<f32>, p: vec2<f32>) -> f32 {
+    let pa = p - e.xy;
+    let ba = e.zw - e.xy;
+    let h = clamp(dot(pa, ba) / max(dot(ba, ba), 1e-12), 0., 1.);
+    return length(pa - ba * h);
+}
+
+fn gradient_color(base: i32) -> vec4<f32> {
+    let header = path_data[base];
+    let m0 = path_data[base + 1];
+    let m1 = path_data[base + 2];
+    let geometry = path_data[base + 3];
+    let p = vec2<f32>(m0.x * pixel.x + m0.z * pixel.y + m1.x, m0.y * pixel.x + m0.w * pixel.y + m1.y);
+    var t = 0.;
+    if(header.x == 1.) {
+        let d = geometry.zw - geometry.xy;
+        t = dot(p - geometry.xy, d) / max(dot(d, d), 1e-12);
+    } else {
+        // Solves for the circle, interpolated from the focal point to the outer circle, that passes through p.
+        let d = geometry.xy - geometry.zw;
+        let q = p - geometry.zw;
+        let a = dot(d, d) - m1.z * m1.z;
+        let b = dot(q, d);
+        t = (b - sqrt(max(b * b - a * dot(q, q), 0.))) / min(a, -1e-12);
+    }
+    if(header.y == 1.) {
+        t = 1. - abs(fract(t * 0.5) * 2. - 1.);
+    } else if(header.y == 2.) {
+        t = fract(t);
+    }
+    let count = i32(header.z);
+    var previous = path_data[base + 5];
+    if(t <= path_data[base + 4].x) {
+        return previous;
+    }
+    for(var k = 1; k < count; k++) {
+        let offset = path_data[base + 4 + k * 2].x;
+        let current = path_data[base + 5 + k * 2];
+        if(t <= offset) {
+            let previous_offset = path_data[base + 2 + k * 2].x;
+            return mix(previous, current, (t - previous_offset) / max(offset - previous_offset, 1e-6));
+        }
+        previous = current;
+    }
+    return previous;
+}
+
+fn draw_path(even_odd: bool) {
+    if(pixel.x < data[i + 1] - 1. || pixel.x > data[i + 2] + 1. || pixel.y < data[i + 3] - 1. || pixel.y > data[i + 4] + 1.) {
         return;
     }
-    color = vec4<f32>(data[i + 4], data[i + 5], data[i + 6], data[i + 7]);
-    i += 8;
+    let first = i32(data[i + 15]);
+    let last = first + i32(data[i + 16]);
+    var coverage = 0.;
+    if(even_odd) {
+        var inside = false;
+        var distance = 1e9;
+        for(var k = first; k < last; k++) {
+            let e = path_data[k];
+            distance = min(distance, edge_distance(e, pixel));
+            if((e.y <= pixel.y) != (e.w <= pixel.y) && e.x + (pixel.y - e.y) / (e.w - e.y) * (e.z - e.x) > pixel.x) {
+                inside = !inside;
+            }
+        }
+        coverage = clamp(select(0.5 - distance, 0.5 + distance, inside), 0., 1.);
+    } else {
+        var area = 0.;
+        for(var k = first; k < last; k++) {
+            area += edge_area(path_data[k], pixel - 0.5);
+        }
+        coverage = min(abs(area), 1.);
+    }
+    var c = record_color();
+    if(data[i + 22] > 0.) {
+        c *= gradient_color(last);
+    }
+    blend(c, coverage * box_coverage(local_point()));
 }
 
-@fragment fn fs_main(@builtin(position) pixel_pos: vec4<f32>) -> @location(0) vec4<f32> {
-    px = pixel_pos.x;
-    py = pixel_pos.y;
-    i = 0;
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    pixel = in.position.xy;
     color = vec4<f32>(0.);
     let data_length = i32(arrayLength(&data));
-    while(i < data_length) {
-        switch i32(read_f32()) {
+    for(i = i32(in.start) * RECORD_SIZE; i + RECORD_SIZE <= data_length; i += RECORD_SIZE) {
+        let op = i32(data[i]);
+        if(op == 5) { break; }
+        switch op {
             case 1: { draw_rect(); }
+            case 2: { draw_shadow(); }
+            case 3: { draw_inset_shadow(); }
+            case 4: { draw_backdrop(); }
+            case 6: { draw_path(false); }
+            case 7: { draw_path(true); }
             default {}
         }
     }
     return color;
-}
\ No newline at end of file
+}
+
+@fragment fn fs_clear() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.);
+}
@@ -0,0 +1,759 @@
+use std::{sync::Arc, collections::HashMap, f32::consts::PI};
+
+use roxmltree::Node;
+
+use crate::{
+    Path, Paint, Gradient, GradientKind, SpreadMethod, DrawOp, DrawStyle, FillRule, Stroke, LineJoin, LineCap,
+    Color, Matrix, Rect, Element, Context, CoordValue, css::{parse_color, parse_number}
+};
+
+const XLINK: &str = "http://www.w3.org/1999/xlink";
+const MAX_DEPTH: usize = 32;
+const KAPPA: f32 = 0.552_284_8;
+
+// A parsed SVG document, kept as vector paths so that it stays crisp at any size.
+#[derive(Debug, PartialEq)]
+pub struct Svg {
+    width: f32,
+    height: f32,
+    view_box: Rect,
+    align: Option<(f32, f32)>,
+    slice: bool,
+    pub(crate) ops: Vec<DrawOp>
+}
+impl Svg {
+    pub fn parse(src: &str) -> Option<Self> {
+        let document = match roxmltree::Document::parse(src) {
+            Ok(v) => v,
+            Err(e) => {
+                log::warn!("Failed to parse SVG: {e}");
+                return None
+            }
+        };
+        let root = document.root_element();
+        if root.tag_name().name() != "svg" {
+            log::warn!("Failed to parse SVG: the root element is <{}>", root.tag_name().name());
+            return None
+        }
+        let view_box = root.attribute("viewBox").and_then(parse_numbers)
+            .filter(|v| v.len() == 4 && v[2] > 0. && v[3] > 0.)
+            .map(|v| Rect::new(v[0], v[1], v[0] + v[2], v[1] + v[3]));
+        let size = |name: &str, fallback: f32| root.attribute(name)
+            .filter(|v| !v.trim().ends_with('%'))
+            .and_then(|v| parse_length(v, 0.))
+            .filter(|v| *v > 0.)
+            .unwrap_or(fallback);
+        let width = size("width", view_box.map_or(100., |v| v.x1 - v.x0));
+        let height = size("height", view_box.map_or(100., |v| v.y1 - v.y0));
+        let view_box = view_box.unwrap_or(Rect::new(0., 0., width, height));
+        let (align, slice) = parse_aspect_ratio(root.attribute("preserveAspectRatio").unwrap_or(""));
+
+        let mut parser = Parser {
+            ids: document.descendants().filter_map(|n| Some((n.attribute("id")?, n))).collect(),
+            viewport: (view_box.x1 - view_box.x0, view_box.y1 - view_box.y0),
+            ops: Vec::new()
+        };
+        let state = State::default().apply(root)?;
+        parser.children(root, &state, 0);
+        Some(Self { width, height, view_box, align, slice, ops: parser.ops })
+    }
+    pub fn load(path: impl AsRef<std::path::Path>) -> Option<Self> {
+        match std::fs::read_to_string(path.as_ref()) {
+            Ok(src) => Self::parse(&src),
+            Err(e) => {
+                log::warn!("Failed to load {}: {e}", path.as_ref().display());
+                None
+            }
+        }
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.width
+    }
+    pub fn get_height(&self) -> f32 {
+        self.height
+    }
+
+    // Maps the view box into a box of the given size, following preserveAspectRatio.
+    pub(crate) fn fit(&self, width: f32, height: f32) -> Matrix {
+        let (vw, vh) = (self.view_box.x1 - self.view_box.x0, self.view_box.y1 - self.view_box.y0);
+        let (sx, sy) = (width / vw, height / vh);
+        let origin = Matrix::translate(-self.view_box.x0, -self.view_box.y0);
+        match self.align {
+            None => Matrix::scale(sx, sy).multiply(&origin),
+            Some((ax, ay)) => {
+                let s = if self.slice { sx.max(sy) } else { sx.min(sy) };
+                Matrix::translate((width - vw * s) * ax, (height - vh * s) * ay).multiply(&Matrix::scale(s, s)).multiply(&origin)
+            }
+        }
+    }
+}
+
+impl Element {
+    // Shows the SVG as the background of this element, sized to the SVG when no size is set.
+    pub fn set_image(&self, svg: Arc<Svg>) {
+        if self.style.width.get() == CoordValue::Auto { self.style.width.set_px(svg.width) }
+        if self.style.height.get() == CoordValue::Auto { self.style.height.set_px(svg.height) }
+        self.style.background.set(svg.into())
+    }
+    // Loads the SVG file on its own thread once the container of this element updates, so it never blocks a frame.
+    pub fn load_image(&self, src: &str) {
+        self.set_image_src(src)
+    }
+    pub(crate) fn load_pending_images(&self, c: &Context) {
+        let mut pending = Vec::new();
+        self.take_image_srcs(&mut pending);
+        for (element, src) in pending {
+            let load = c.spawn_blocking({
+                let src = src.clone();
+                move || Svg::load(src)
+            });
+            c.spawn(async move {
+                match load.await.flatten() {
+                    Some(svg) => element.set_image(Arc::new(svg)),
+                    None => log::warn!("Failed to load image `{src}`")
+                }
+            });
+        }
+    }
+}
+impl From<Arc<Svg>> for crate::BackgroundType {
+    fn from(svg: Arc<Svg>) -> Self {
+        Self::Svg(svg)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PaintSpec {
+    None,
+    Color(Color),
+    CurrentColor,
+    Url(String, Option<Color>)
+}
+
+#[derive(Clone)]
+struct State {
+    fill: PaintSpec,
+    fill_opacity: f32,
+    fill_rule: FillRule,
+    stroke: PaintSpec,
+    stroke_opacity: f32,
+    stroke_style: Stroke,
+    color: Color,
+    visible: bool,
+    opacity: f32,
+    transform: Matrix
+}
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            fill: PaintSpec::Color(Color::BLACK),
+            fill_opacity: 1.,
+            fill_rule: FillRule::NonZero,
+            stroke: PaintSpec::None,
+            stroke_opacity: 1.,
+            stroke_style: Stroke::default(),
+            color: Color::BLACK,
+            visible: true,
+            opacity: 1.,
+            transform: Matrix::IDENTITY
+        }
+    }
+}
+impl State {
+    // Returns the state of a child element, or None when it is not displayed.
+    fn apply(&self, node: Node) -> Option<Self> {
+        let mut res = self.clone();
+        let style: HashMap<&str, &str> = node.attribute("style").unwrap_or("").split(';')
+            .filter_map(|d| d.split_once(':'))
+            .map(|(k, v)| (k.trim(), v.trim()))
+            .collect();
+        let get = |name: &str| style.get(name).copied().or_else(|| node.attribute(name)).map(str::trim).filter(|v| *v != "inherit");
+        if get("display") == Some("none") { return None }
+
+        if let Some(v) = get("color").and_then(parse_color) { res.color = v }
+        if let Some(v) = get("fill").and_then(parse_paint) { res.fill = v }
+        if let Some(v) = get("stroke").and_then(parse_paint) { res.stroke = v }
+        if let Some(v) = get("fill-opacity").and_then(parse_opacity) { res.fill_opacity = v }
+        if let Some(v) = get("stroke-opacity").and_then(parse_opacity) { res.stroke_opacity = v }
+        if let Some(v) = get("opacity").and_then(parse_opacity) { res.opacity *= v }
+        match get("fill-rule") {
+            Some("evenodd") => res.fill_rule = FillRule::EvenOdd,
+            Some("nonzero") => res.fill_rule = FillRule::NonZero,
+            _ => {}
+        }
+        match get("visibility") {
+            Some("hidden" | "collapse") => res.visible = false,
+            Some("visible") => res.visible = true,
+            _ => {}
+        }
+        if let Some(v) = get("stroke-width").and_then(|v| parse_length(v, 0.)) { res.stroke_style.width = v }
+        if let Some(v) = get("stroke-miterlimit").and_then(parse_number).filter(|v| *v >= 1.) { res.stroke_style.miter_limit = v }
+        if let Some(v) = get("stroke-dashoffset").and_then(|v| parse_length(v, 0.)) { res.stroke_style.dash_offset = v }
+        match get("stroke-dasharray") {
+            Some("none") => res.stroke_style.dash = Vec::new(),
+            Some(v) => if let Some(v) = parse_numbers(v) { res.stroke_style.dash = v },
+            None => {}
+        }
+        match get("stroke-linejoin") {
+            Some("miter" | "miter-clip" | "arcs") => res.stroke_style.join = LineJoin::Miter,
+            Some("round") => res.stroke_style.join = LineJoin::Round,
+            Some("bevel") => res.stroke_style.join = LineJoin::Bevel,
+            _ => {}
+        }
+        match get("stroke-linecap") {
+            Some("butt") => res.stroke_style.cap = LineCap::Butt,
+            Some("round") => res.stroke_style.cap = LineCap::Round,
+            Some("square") => res.stroke_style.cap = LineCap::Square,
+            _ => {}
+        }
+        if let Some(v) = node.attribute("transform") {
+            match parse_transform(v) {
+                Some(m) => res.transform = res.transform.multiply(&m),
+                None => log::warn!("Invalid SVG transform: {v}")
+            }
+        }
+        Some(res)
+    }
+}
+
+struct Parser<'a, 'input> {
+    ids: HashMap<&'a str, Node<'a, 'input>>,
+    viewport: (f32, f32),
+    ops: Vec<DrawOp>
+}
+impl<'a, 'input> Parser<'a, 'input> {
+    fn children(&mut self, node: Node<'a, 'input>, state: &State, depth: usize) {
+        for child in node.children().filter(|n| n.is_element()) {
+            if let Some(state) = state.apply(child) {
+                self.node(child, &state, depth)
+            }
+        }
+    }
+
+    fn node(&mut self, node: Node<'a, 'input>, state: &State, depth: usize) {
+        if depth > MAX_DEPTH { return }
+        match node.tag_name().name() {
+            "g" | "a" | "svg" | "switch" => self.children(node, state, depth + 1),
+            "use" => {
+                let target = href(node).and_then(|id| self.ids.get(id).copied());
+                let (x, y) = (self.length(node, "x", 0), self.length(node, "y", 1));
+                let mut state = state.clone();
+                state.transform = state.transform.multiply(&Matrix::translate(x, y));
+                if let Some(state) = target.and_then(|t| state.apply(t).map(|s| (t, s))) {
+                    self.node(state.0, &state.1, depth + 1)
+                }
+            }
+            _ => if let Some(path) = self.shape(node) {
+                if state.visible { self.draw(path, state) }
+            }
+        }
+    }
+
+    // Percentages are relative to the width, the height or the normalized diagonal of the view box.
+    fn reference(&self, axis: usize) -> f32 {
+        match axis {
+            0 => self.viewport.0,
+            1 => self.viewport.1,
+            _ => ((self.viewport.0.powi(2) + self.viewport.1.powi(2)) / 2.).sqrt()
+        }
+    }
+    fn length(&self, node: Node, name: &str, axis: usize) -> f32 {
+        node.attribute(name).and_then(|v| parse_length(v, self.reference(axis))).unwrap_or(0.)
+    }
+
+    fn shape(&self, node: Node) -> Option<Path> {
+        let l = |name: &str, axis: usize| self.length(node, name, axis);
+        Some(match node.tag_name().name() {
+            "path" => parse_path_data(node.attribute("d")?),
+            "rect" => {
+                let (x, y, w, h) = (l("x", 0), l("y", 1), l("width", 0), l("height", 1));
+                if w <= 0. || h <= 0. { return None }
+                let (rx, ry) = match (node.has_attribute("rx"), node.has_attribute("ry")) {
+                    (true, false) => (l("rx", 0), l("rx", 0)),
+                    (false, true) => (l("ry", 1), l("ry", 1)),
+                    _ => (l("rx", 0), l("ry", 1))
+                };
+                rounded_rect(x, y, w, h, rx.clamp(0., w / 2.), ry.clamp(0., h / 2.))
+            }
+            "circle" => {
+                let r = l("r", 2);
+                if r <= 0. { return None }
+                ellipse(l("cx", 0), l("cy", 1), r, r)
+            }
+            "ellipse" => {
+                let (rx, ry) = (l("rx", 0), l("ry", 1));
+                if rx <= 0. || ry <= 0. { return None }
+                ellipse(l("cx", 0), l("cy", 1), rx, ry)
+            }
+            "line" => Path::new().move_to(l("x1", 0), l("y1", 1)).line_to(l("x2", 0), l("y2", 1)),
+            name @ ("polyline" | "polygon") => {
+                let points = parse_numbers(node.attribute("points")?)?;
+                let mut path = Path::new();
+                for p in points.chunks_exact(2) {
+                    path = path.line_to(p[0], p[1])
+                }
+                if name == "polygon" { path.close() } else { path }
+            }
+            _ => return None
+        })
+    }
+
+    fn draw(&mut self, path: Path, state: &State) {
+        if path.is_empty() { return }
+        let bounds = path.bounds();
+        if let Some(paint) = self.paint(&state.fill, state, bounds, state.fill_opacity) {
+            self.ops.push(DrawOp { path: path.clone(), paint, style: DrawStyle::Fill(state.fill_rule), transform: state.transform })
+        }
+        if state.stroke_style.width > 0. {
+            if let Some(paint) = self.paint(&state.stroke, state, bounds, state.stroke_opacity) {
+                self.ops.push(DrawOp { path, paint, style: DrawStyle::Stroke(state.stroke_style.clone()), transform: state.transform })
+            }
+        }
+    }
+
+    fn paint(&self, spec: &PaintSpec, state: &State, bounds: Rect, opacity: f32) -> Option<Paint> {
+        let paint = match spec {
+            PaintSpec::None => return None,
+            PaintSpec::Color(c) => Paint::Solid(*c),
+            PaintSpec::CurrentColor => Paint::Solid(state.color),
+            PaintSpec::Url(id, fallback) => match self.gradient(id, bounds) {
+                Some(v) => v,
+                None => Paint::Solid((*fallback)?)
+            }
+        }.with_opacity(opacity * state.opacity);
+        if paint.is_visible() { Some(paint) } else { None }
+    }
+
+    fn gradient(&self, id: &str, bounds: Rect) -> Option<Paint> {
+        let node = *self.ids.get(id)?;
+        let kind = node.tag_name().name();
+        if kind != "linearGradient" && kind != "radialGradient" { return None }
+        // Attributes and stops that are not set are taken from the referenced gradient.
+        let chain: Vec<Node> = std::iter::successors(Some(node), |n| href(*n).and_then(|id| self.ids.get(id).copied()))
+            .take(MAX_DEPTH)
+            .collect();
+        let attribute = |name: &str| chain.iter().find_map(|n| n.attribute(name));
+        let stops_node = chain.iter().find(|n| n.children().any(|c| c.tag_name().name() == "stop"))?;
+
+        let mut stops = Vec::new();
+        for stop in stops_node.children().filter(|c| c.tag_name().name() == "stop") {
+            let state = State::default().apply(stop).unwrap_or_default();
+            let style: HashMap<&str, &str> = stop.attribute("style").unwrap_or("").split(';')
+                .filter_map(|d| d.split_once(':'))
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .collect();
+            let get = |name: &str| style.get(name).copied().or_else(|| stop.attribute(name));
+            let offset = stop.attribute("offset").and_then(parse_fraction).unwrap_or(0.).clamp(0., 1.);
+            let mut color = match get("stop-color").map(str::trim) {
+                Some("currentColor") => state.color,
+                Some(v) => parse_color(v).unwrap_or(Color::BLACK),
+                None => Color::BLACK
+            };
+            color.a *= get("stop-opacity").and_then(parse_opacity).unwrap_or(1.);
+            stops.push((offset, color))
+        }
+        if stops.len() == 1 { return Some(Paint::Solid(stops[0].1)) }
+
+        let user_space = attribute("gradientUnits") == Some("userSpaceOnUse");
+        let (w, h) = (bounds.x1 - bounds.x0, bounds.y1 - bounds.y0);
+        if !user_space && (w <= 0. || h <= 0.) { return None }
+        let number = |name: &str, default: f32, axis: usize| {
+            let v = match attribute(name) { Some(v) => v, None => return default };
+            if user_space {
+                parse_length(v, self.reference(axis)).unwrap_or(default)
+            } else {
+                parse_fraction(v).unwrap_or(default)
+            }
+        };
+        let full = |axis: usize| if user_space { self.reference(axis) } else { 1. };
+        let kind = if kind == "linearGradient" {
+            GradientKind::Linear { x1: number("x1", 0., 0), y1: number("y1", 0., 1), x2: number("x2", full(0), 0), y2: number("y2", 0., 1) }
+        } else {
+            let (cx, cy) = (number("cx", 0.5 * full(0), 0), number("cy", 0.5 * full(1), 1));
+            GradientKind::Radial { cx, cy, r: number("r", 0.5 * full(2), 2), fx: number("fx", cx, 0), fy: number("fy", cy, 1) }
+        };
+        let spread = match attribute("spreadMethod") {
+            Some("reflect") => SpreadMethod::Reflect,
+            Some("repeat") => SpreadMethod::Repeat,
+            _ => SpreadMethod::Pad
+        };
+        let units = if user_space { Matrix::IDENTITY } else { Matrix::new(w, 0., 0., h, bounds.x0, bounds.y0) };
+        let transform = units.multiply(&attribute("gradientTransform").and_then(parse_transform).unwrap_or_default());
+        let mut gradient = Gradient { kind, stops: Vec::new(), spread, transform };
+        for (offset, color) in stops {
+            gradient = gradient.stop(offset, color)
+        }
+        Some(Paint::Gradient(gradient))
+    }
+}
+
+fn href<'a>(node: Node<'a, '_>) -> Option<&'a str> {
+    node.attribute("href").or_else(|| node.attribute((XLINK, "href")))?.trim().strip_prefix('#')
+}
+
+fn parse_paint(src: &str) -> Option<PaintSpec> {
+    let src = src.trim();
+    if src == "none" { return Some(PaintSpec::None) }
+    if src == "currentColor" { return Some(PaintSpec::CurrentColor) }
+    if let Some(rest) = src.strip_prefix("url(") {
+        let (url, fallback) = rest.split_once(')')?;
+        let id = url.trim().trim_matches(|c| c == '"' || c == '\'').strip_prefix('#')?;
+        let fallback = match fallback.trim() {
+            "" | "none" => None,
+            v => parse_color(v)
+        };
+        return Some(PaintSpec::Url(id.to_string(), fallback))
+    }
+    parse_color(src).map(PaintSpec::Color)
+}
+
+fn parse_opacity(src: &str) -> Option<f32> {
+    parse_fraction(src).map(|v| v.clamp(0., 1.))
+}
+
+fn parse_fraction(src: &str) -> Option<f32> {
+    let src = src.trim();
+    match src.strip_suffix('%') {
+        Some(v) => Some(parse_number(v)? / 100.),
+        None => parse_number(src)
+    }
+}
+
+fn parse_length(src: &str, reference: f32) -> Option<f32> {
+    let src = src.trim();
+    let units = [("px", 1.), ("pt", 4. / 3.), ("pc", 16.), ("mm", 3.779_528), ("cm", 37.795_28), ("in", 96.), ("em", 16.), ("ex", 8.)];
+    if let Some(v) = src.strip_suffix('%') { return Some(parse_number(v)? / 100. * reference) }
+    for (unit, scale) in units {
+        if let Some(v) = src.strip_suffix(unit) { return Some(parse_number(v)? * scale) }
+    }
+    parse_number(src)
+}
+
+fn parse_numbers(src: &str) -> Option<Vec<f32>> {
+    src.split(|c: char| c.is_whitespace() || c == ',').filter(|v| !v.is_empty()).map(parse_number).collect()
+}
+
+fn parse_aspect_ratio(src: &str) -> (Option<(f32, f32)>, bool) {
+    let mut parts = src.split_whitespace();
+    let align = match parts.next().unwrap_or("xMidYMid") {
+        "none" => return (None, false),
+        v if v.len() == 8 => {
+            let axis = |v: &str| match v { "Min" => 0., "Max" => 1., _ => 0.5 };
+            (axis(&v[1..4]), axis(&v[5..8]))
+        }
+        _ => (0.5, 0.5)
+    };
+    (Some(align), parts.next() == Some("slice"))
+}
+
+pub(crate) fn parse_transform(src: &str) -> Option<Matrix> {
+    let mut res = Matrix::IDENTITY;
+    let mut rest = src.trim();
+    while !rest.is_empty() {
+        let (name, tail) = rest.split_once('(')?;
+        let (args, tail) = tail.split_once(')')?;
+        let a = parse_numbers(args)?;
+        let m = match (name.trim(), a.len()) {
+            ("matrix", 6) => Matrix::new(a[0], a[1], a[2], a[3], a[4], a[5]),
+            ("translate", 1) => Matrix::translate(a[0], 0.),
+            ("translate", 2) => Matrix::translate(a[0], a[1]),
+            ("scale", 1) => Matrix::scale(a[0], a[0]),
+            ("scale", 2) => Matrix::scale(a[0], a[1]),
+            ("rotate", 1) => Matrix::rotate(a[0].to_radians()),
+            ("rotate", 3) => Matrix::translate(a[1], a[2]).multiply(&Matrix::rotate(a[0].to_radians())).multiply(&Matrix::translate(-a[1], -a[2])),
+            ("skewX", 1) => Matrix::skew(a[0].to_radians(), 0.),
+            ("skewY", 1) => Matrix::skew(0., a[0].to_radians()),
+            _ => return None
+        };
+        res = res.multiply(&m);
+        rest = tail.trim_start().trim_start_matches(',').trim_start()
+    }
+    Some(res)
+}
+
+fn ellipse(cx: f32, cy: f32, rx: f32, ry: f32) -> Path {
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    Path::new()
+        .move_to(cx + rx, cy)
+        .cubic_to(cx + rx, cy + ky, cx + kx, cy + ry, cx, cy + ry)
+        .cubic_to(cx - kx, cy + ry, cx - rx, cy + ky, cx - rx, cy)
+        .cubic_to(cx - rx, cy - ky, cx - kx, cy - ry, cx, cy - ry)
+        .cubic_to(cx + kx, cy - ry, cx + rx, cy - ky, cx + rx, cy)
+        .close()
+}
+
+fn rounded_rect(x: f32, y: f32, w: f32, h: f32, rx: f32, ry: f32) -> Path {
+    if rx <= 0. || ry <= 0. { return Path::new().rect(x, y, w, h) }
+    let (kx, ky) = (rx * (1. - KAPPA), ry * (1. - KAPPA));
+    let (x1, y1) = (x + w, y + h);
+    Path::new()
+        .move_to(x + rx, y)
+        .line_to(x1 - rx, y)
+        .cubic_to(x1 - kx, y, x1, y + ky, x1, y + ry)
+        .line_to(x1, y1 - ry)
+        .cubic_to(x1, y1 - ky, x1 - kx, y1, x1 - rx, y1)
+        .line_to(x + rx, y1)
+        .cubic_to(x + kx, y1, x, y1 - ky, x, y1 - ry)
+        .line_to(x, y + ry)
+        .cubic_to(x, y + ky, x + kx, y, x + rx, y)
+        .close()
+}
+
+struct PathTokens<'a> {
+    src: &'a [u8],
+    pos: usize
+}
+impl PathTokens<'_> {
+    fn skip(&mut self) {
+        while self.pos < self.src.len() && (self.src[self.pos].is_ascii_whitespace() || self.src[self.pos] == b',') {
+            self.pos += 1
+        }
+    }
+    fn command(&mut self) -> Option<u8> {
+        self.skip();
+        let c = *self.src.get(self.pos)?;
+        if c.is_ascii_alphabetic() && c != b'e' && c != b'E' {
+            self.pos += 1;
+            Some(c)
+        } else {
+            None
+        }
+    }
+    fn number(&mut self) -> Option<f32> {
+        self.skip();
+        let start = self.pos;
+        let at = |i: usize| self.src.get(i).copied().unwrap_or(0);
+        let mut i = self.pos;
+        if matches!(at(i), b'+' | b'-') { i += 1 }
+        while at(i).is_ascii_digit() { i += 1 }
+        if at(i) == b'.' {
+            i += 1;
+            while at(i).is_ascii_digit() { i += 1 }
+        }
+        if matches!(at(i), b'e' | b'E') && (at(i + 1).is_ascii_digit() || (matches!(at(i + 1), b'+' | b'-') && at(i + 2).is_ascii_digit())) {
+            i += 2;
+            while at(i).is_ascii_digit() { i += 1 }
+        }
+        let v = std::str::from_utf8(&self.src[start..i]).ok()?.parse::<f32>().ok()?;
+        self.pos = i;
+        Some(v)
+    }
+    fn flag(&mut self) -> Option<bool> {
+        self.skip();
+        let c = *self.src.get(self.pos)?;
+        self.pos += 1;
+        match c { b'0' => Some(false), b'1' => Some(true), _ => None }
+    }
+    fn at_end(&mut self) -> bool {
+        self.skip();
+        self.pos >= self.src.len()
+    }
+}
+
+// Parses path data, keeping everything up to the first error as SVG requires.
+pub(crate) fn parse_path_data(src: &str) -> Path {
+    let mut t = PathTokens { src: src.as_bytes(), pos: 0 };
+    let mut path = Path::new();
+    let (mut current, mut start) = ((0f32, 0f32), (0f32, 0f32));
+    let mut control: Option<(u8, (f32, f32))> = None;
+    let mut command = None;
+    while !t.at_end() {
+        if let Some(c) = t.command() {
+            command = Some(c)
+        } else if matches!(command, None | Some(b'z' | b'Z')) {
+            break
+        }
+        let c = command.unwrap();
+        let (ox, oy) = if c.is_ascii_lowercase() { current } else { (0., 0.) };
+        let mut point = || Some((t.number()? + ox, t.number()? + oy));
+        let segment = match c.to_ascii_uppercase() {
+            b'M' => point().map(|p| {
+                path = std::mem::take(&mut path).move_to(p.0, p.1);
+                start = p;
+                command = Some(if c == b'm' { b'l' } else { b'L' });
+                (p, None)
+            }),
+            b'L' => point().map(|p| {
+                path = std::mem::take(&mut path).line_to(p.0, p.1);
+                (p, None)
+            }),
+            b'H' => t.number().map(|x| {
+                let p = (x + ox, current.1);
+                path = std::mem::take(&mut path).line_to(p.0, p.1);
+                (p, None)
+            }),
+            b'V' => t.number().map(|y| {
+                let p = (current.0, y + oy);
+                path = std::mem::take(&mut path).line_to(p.0, p.1);
+                (p, None)
+            }),
+            b'C' => (|| Some((point()?, point()?, point()?)))().map(|(c1, c2, p)| {
+                path = std::mem::take(&mut path).cubic_to(c1.0, c1.1, c2.0, c2.1, p.0, p.1);
+                (p, Some((b'C', c2)))
+            }),
+            b'S' => (|| Some((point()?, point()?)))().map(|(c2, p)| {
+                let c1 = match control { Some((b'C', c)) => (2. * current.0 - c.0, 2. * current.1 - c.1), _ => current };
+                path = std::mem::take(&mut path).cubic_to(c1.0, c1.1, c2.0, c2.1, p.0, p.1);
+                (p, Some((b'C', c2)))
+            }),
+            b'Q' => (|| Some((point()?, point()?)))().map(|(c1, p)| {
+                path = std::mem::take(&mut path).quad_to(c1.0, c1.1, p.0, p.1);
+                (p, Some((b'Q', c1)))
+            }),
+            b'T' => point().map(|p| {
+                let c1 = match control { Some((b'Q', c)) => (2. * current.0 - c.0, 2. * current.1 - c.1), _ => current };
+                path = std::mem::take(&mut path).quad_to(c1.0, c1.1, p.0, p.1);
+                (p, Some((b'Q', c1)))
+            }),
+            b'A' => (|| Some((t.number()?, t.number()?, t.number()?, t.flag()?, t.flag()?, t.number()? + ox, t.number()? + oy)))()
+                .map(|(rx, ry, angle, large, sweep, x, y)| {
+                    path = arc_to(std::mem::take(&mut path), current, rx, ry, angle, large, sweep, (x, y));
+                    ((x, y), None)
+                }),
+            b'Z' => {
+                path = std::mem::take(&mut path).close();
+                Some((start, None))
+            }
+            _ => None
+        };
+        match segment {
+            Some((p, c)) => (current, control) = (p, c),
+            None => break
+        }
+    }
+    path
+}
+
+// Converts an SVG endpoint arc to cubic curves of at most a quarter turn each.
+#[allow(clippy::too_many_arguments)]
+fn arc_to(path: Path, from: (f32, f32), rx: f32, ry: f32, angle: f32, large: bool, sweep: bool, to: (f32, f32)) -> Path {
+    if from == to { return path }
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if rx == 0. || ry == 0. { return path.line_to(to.0, to.1) }
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (dx, dy) = ((from.0 - to.0) / 2., (from.1 - to.1) / 2.);
+    let (x1, y1) = (cos * dx + sin * dy, -sin * dx + cos * dy);
+    let lambda = x1 * x1 / (rx * rx) + y1 * y1 / (ry * ry);
+    if lambda > 1. {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt()
+    }
+    let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let coef = (num / den).max(0.).sqrt() * if large == sweep { -1. } else { 1. };
+    let (cxp, cyp) = (coef * rx * y1 / ry, -coef * ry * x1 / rx);
+    let (cx, cy) = (cos * cxp - sin * cyp + (from.0 + to.0) / 2., sin * cxp + cos * cyp + (from.1 + to.1) / 2.);
+    let vector_angle = |ux: f32, uy: f32, vx: f32, vy: f32| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+    let theta = vector_angle(1., 0., (x1 - cxp) / rx, (y1 - cyp) / ry);
+    let mut delta = vector_angle((x1 - cxp) / rx, (y1 - cyp) / ry, (-x1 - cxp) / rx, (-y1 - cyp) / ry);
+    if !sweep && delta > 0. { delta -= 2. * PI }
+    if sweep && delta < 0. { delta += 2. * PI }
+
+    let map = |u: f32, v: f32| (cx + cos * rx * u - sin * ry * v, cy + sin * rx * u + cos * ry * v);
+    let count = (delta.abs() / (PI / 2.)).ceil().max(1.) as usize;
+    let step = delta / count as f32;
+    let k = 4. / 3. * (step / 4.).tan();
+    let mut path = path;
+    for i in 0..count {
+        let a0 = theta + step * i as f32;
+        let a1 = a0 + step;
+        let c1 = map(a0.cos() - k * a0.sin(), a0.sin() + k * a0.cos());
+        let c2 = map(a1.cos() + k * a1.sin(), a1.sin() - k * a1.cos());
+        let p = if i + 1 == count { to } else { map(a1.cos(), a1.sin()) };
+        path = path.cubic_to(c1.0, c1.1, c2.0, c2.1, p.0, p.1)
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn svg(attributes: &str, content: &str) -> Svg {
+        Svg::parse(&format!(r#"<svg xmlns="http://www.w3.org/2000/svg" {attributes}>{content}</svg>"#)).unwrap()
+    }
+    fn color(src: &str) -> Color {
+        parse_color(src).unwrap()
+    }
+
+    #[test]
+    fn size_and_view_box() {
+        let cases = [
+            (r#"width="40" height="20""#, (40., 20.), Rect::new(0., 0., 40., 20.)),
+            (r#"viewBox="10 10 50 25""#, (50., 25.), Rect::new(10., 10., 60., 35.)),
+            (r#"width="100px" viewBox="0 0 50 25""#, (100., 25.), Rect::new(0., 0., 50., 25.)),
+            (r#"width="50%" viewBox="0 0 -5 10""#, (100., 100.), Rect::new(0., 0., 100., 100.))
+        ];
+        for (attributes, size, view_box) in cases {
+            let svg = svg(attributes, "");
+            assert_eq!((svg.get_width(), svg.get_height()), size, "{attributes}");
+            assert_eq!(svg.view_box, view_box, "{attributes}");
+        }
+        assert!(Svg::parse("<div></div>").is_none());
+        assert!(Svg::parse("<svg>").is_none());
+    }
+
+    #[test]
+    fn fit() {
+        // A 100x50 view box drawn into a 200x200 box.
+        let cases = [
+            ("", (0., 50.), (200., 150.)),
+            ("none", (0., 0.), (200., 200.)),
+            ("xMinYMin", (0., 0.), (200., 100.)),
+            ("xMaxYMax", (0., 100.), (200., 200.)),
+            ("xMidYMid slice", (-100., 0.), (300., 200.)),
+            ("xMinYMid slice", (0., 0.), (400., 200.))
+        ];
+        for (ratio, top_left, bottom_right) in cases {
+            let svg = svg(&format!(r#"viewBox="10 0 100 50" preserveAspectRatio="{ratio}""#), "");
+            let m = svg.fit(200., 200.);
+            assert_eq!(m.apply(10., 0.), top_left, "{ratio}");
+            assert_eq!(m.apply(110., 50.), bottom_right, "{ratio}");
+        }
+    }
+
+    #[test]
+    fn inheritance() {
+        let svg = svg("", r#"
+            <g fill="red" opacity="0.5" stroke="blue" stroke-width="2">
+                <rect width="10" height="10"/>
+                <g style="fill: green; stroke: none" fill-rule="evenodd">
+                    <rect width="10" height="10" opacity="0.5"/>
+                    <rect width="10" height="10" fill="inherit" color="blue" stroke="currentColor"/>
+                </g>
+                <rect width="10" height="10" display="none"/>
+                <rect width="10" height="10" visibility="hidden"/>
+            </g>
+        "#);
+        let paints: Vec<(Paint, bool)> = svg.ops.iter().map(|op| (op.paint.clone(), matches!(op.style, DrawStyle::Stroke(_)))).collect();
+        assert_eq!(paints, [
+            (Paint::Solid(color("red")).with_opacity(0.5), false),
+            (Paint::Solid(color("blue")).with_opacity(0.5), true),
+            (Paint::Solid(color("green")).with_opacity(0.25), false),
+            (Paint::Solid(color("green")).with_opacity(0.5), false),
+            (Paint::Solid(color("blue")).with_opacity(0.5), true)
+        ]);
+        assert!(svg.ops[2].is_even_odd());
+        assert!(matches!(&svg.ops[1].style, DrawStyle::Stroke(stroke) if stroke.width == 2.));
+    }
+
+    #[test]
+    fn transforms() {
+        let svg = svg("", r##"
+            <defs><rect id="r" width="1" height="1"/></defs>
+            <g transform="translate(10, 20) scale(2)">
+                <rect width="1" height="1" transform="rotate(90)"/>
+                <use href="#r" x="5" y="5"/>
+            </g>
+        "##);
+        let corners: Vec<(f32, f32)> = svg.ops.iter().map(|op| op.transform.apply(1., 0.)).collect();
+        // Shapes inside <defs> are only drawn through <use>.
+        assert_eq!(corners.len(), 2);
+        let close = |a: (f32, f32), b: (f32, f32)| (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4;
+        assert!(close(corners[0], (10., 22.)), "{corners:?}");
+        assert!(close(corners[1], (22., 30.)), "{corners:?}");
+        assert_eq!(parse_transform("skewX(45) foo(1)"), None);
+        assert_eq!(parse_transform("rotate(90 1 1)").map(|m| m.apply(1., 1.)), Some((1., 1.)));
+    }
+}
@@ -0,0 +1,161 @@
+use std::{collections::HashMap, future::Future, sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, Ordering}}};
+use futures::{channel::oneshot, future::{AbortHandle, Abortable, LocalBoxFuture}, task::{ArcWake, waker}, FutureExt};
+
+type Wake = Arc<dyn Fn() + Send + Sync>;
+
+struct TaskWaker {
+    id: u64,
+    ready: Arc<Mutex<Vec<u64>>>,
+    wake: Wake
+}
+impl ArcWake for TaskWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.ready.lock().unwrap().push(arc_self.id);
+        (arc_self.wake)()
+    }
+}
+
+// Runs futures on the event loop thread. `wake` is called whenever a task is woken, and should make the
+// loop call `run`.
+#[derive(Clone)]
+pub(crate) struct Executor {
+    tasks: Arc<Mutex<HashMap<u64, LocalBoxFuture<'static, ()>>>>,
+    ready: Arc<Mutex<Vec<u64>>>,
+    wake: Wake,
+    next_id: Arc<AtomicU64>
+}
+impl Executor {
+    pub fn new(wake: impl Fn() + Send + Sync + 'static) -> Self {
+        Self {
+            tasks: Default::default(),
+            ready: Default::default(),
+            wake: Arc::new(wake),
+            next_id: Default::default()
+        }
+    }
+    pub fn spawn(&self, future: impl Future<Output = ()> + 'static) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.tasks.lock().unwrap().insert(id, future.boxed_local());
+        self.waker(id).wake()
+    }
+    fn waker(&self, id: u64) -> std::task::Waker {
+        waker(Arc::new(TaskWaker { id, ready: self.ready.clone(), wake: self.wake.clone() }))
+    }
+    // Polls the woken tasks once, returning whether any of them ran.
+    pub fn run(&self) -> bool {
+        let mut ready = std::mem::take(&mut *self.ready.lock().unwrap());
+        ready.sort_unstable();
+        ready.dedup();
+        let mut ran = false;
+        for id in ready {
+            // Tasks are taken out while polled, so they can spawn others.
+            let mut future = match self.tasks.lock().unwrap().remove(&id) { Some(v) => v, None => continue };
+            ran = true;
+            let waker = self.waker(id);
+            if future.as_mut().poll(&mut std::task::Context::from_waker(&waker)).is_pending() {
+                self.tasks.lock().unwrap().insert(id, future);
+            }
+        }
+        ran
+    }
+}
+
+pub struct TaskHandle<T> {
+    result: Arc<Mutex<Option<T>>>,
+    finished: Arc<AtomicBool>,
+    abort: AbortHandle
+}
+impl<T> Clone for TaskHandle<T> {
+    fn clone(&self) -> Self {
+        Self { result: self.result.clone(), finished: self.finished.clone(), abort: self.abort.clone() }
+    }
+}
+impl<T: 'static> TaskHandle<T> {
+    pub(crate) fn spawn(executor: &Executor, future: impl Future<Output = T> + 'static) -> Self {
+        let (abort, registration) = AbortHandle::new_pair();
+        let handle = Self { result: Default::default(), finished: Default::default(), abort };
+        let (result, finished) = (handle.result.clone(), handle.finished.clone());
+        executor.spawn(Abortable::new(future, registration).map(move |v| {
+            if let Ok(v) = v { *result.lock().unwrap() = Some(v) }
+            finished.store(true, Ordering::Relaxed)
+        }));
+        handle
+    }
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.abort.is_aborted()
+    }
+    // The result can only be taken once.
+    pub fn take_result(&self) -> Option<T> {
+        self.result.lock().unwrap().take()
+    }
+    pub fn cancel(&self) {
+        self.abort.abort()
+    }
+}
+
+// Resolves to `None` if the closure panicked.
+pub(crate) fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> impl Future<Output = Option<T>> {
+    let (sender, receiver) = oneshot::channel();
+    std::thread::spawn(move || sender.send(f()).ok());
+    receiver.map(Result::ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    fn executor() -> (Executor, Arc<AtomicUsize>) {
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let counter = wakes.clone();
+        (Executor::new(move || { counter.fetch_add(1, Ordering::Relaxed); }), wakes)
+    }
+
+    #[test]
+    fn wake_on_completion() {
+        let (executor, wakes) = executor();
+        let (sender, receiver) = oneshot::channel();
+        let handle = TaskHandle::spawn(&executor, receiver);
+        assert_eq!(wakes.load(Ordering::Relaxed), 1);
+        assert!(executor.run());
+        assert!(!executor.run());
+        assert!(!handle.is_finished());
+
+        sender.send(5).unwrap();
+        assert_eq!(wakes.load(Ordering::Relaxed), 2);
+        assert!(executor.run());
+        assert!(handle.is_finished());
+        assert_eq!(handle.take_result(), Some(Ok(5)));
+        assert!(executor.tasks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn cancel() {
+        let (executor, wakes) = executor();
+        let alive = Arc::new(());
+        let captured = alive.clone();
+        let handle = TaskHandle::spawn(&executor, async move {
+            let _captured = captured;
+            futures::future::pending::<()>().await
+        });
+        executor.run();
+        assert_eq!(Arc::strong_count(&alive), 2);
+
+        handle.cancel();
+        assert_eq!(wakes.load(Ordering::Relaxed), 2);
+        assert!(executor.run());
+        assert!(handle.is_cancelled() && handle.is_finished());
+        assert_eq!(handle.take_result(), None);
+        assert_eq!(Arc::strong_count(&alive), 1);
+        assert!(executor.tasks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn blocking() {
+        assert_eq!(futures::executor::block_on(spawn_blocking(|| 2 + 2)), Some(4));
+        assert_eq!(futures::executor::block_on(spawn_blocking(|| -> i32 { panic!("blocking task") })), None);
+    }
+}
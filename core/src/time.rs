@@ -0,0 +1,182 @@
+use std::{sync::{Arc, atomic::{AtomicU64, Ordering}}, time::{Duration, Instant}};
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemClock(Instant);
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self(Instant::now())
+    }
+}
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct FakeClock(Arc<AtomicU64>);
+impl FakeClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn set(&self, time: Duration) {
+        self.0.store(time.as_nanos() as u64, Ordering::Relaxed)
+    }
+    pub fn advance(&self, delta: Duration) {
+        self.0.fetch_add(delta.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.0.load(Ordering::Relaxed))
+    }
+}
+
+const MAX_FIXED_STEPS: u32 = 8;
+
+pub(crate) struct FrameTimer {
+    clock: Box<dyn Clock>,
+    last: Option<Duration>,
+    started: bool,
+    pub elapsed: Duration,
+    pub delta: Duration,
+    pub frame: u64,
+    pub fps: f32,
+    fps_window: (Duration, u32),
+    pub fixed_timestep: Duration,
+    fixed_accumulator: Duration
+}
+impl Default for FrameTimer {
+    fn default() -> Self {
+        Self {
+            clock: Box::<SystemClock>::default(),
+            last: None,
+            started: false,
+            elapsed: Duration::ZERO,
+            delta: Duration::ZERO,
+            frame: 0,
+            fps: 0.,
+            fps_window: (Duration::ZERO, 0),
+            fixed_timestep: Duration::from_secs_f64(1. / 60.),
+            fixed_accumulator: Duration::ZERO
+        }
+    }
+}
+impl FrameTimer {
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = clock;
+        self.last = None
+    }
+
+    // Advances to the next frame and returns how many fixed steps are due.
+    pub fn begin_frame(&mut self) -> u32 {
+        let now = self.clock.now();
+        self.delta = self.last.map(|last| now.saturating_sub(last)).unwrap_or_default();
+        self.last = Some(now);
+        self.elapsed += self.delta;
+        if self.started { self.frame += 1 }
+        self.started = true;
+
+        self.fps_window.1 += 1;
+        let window = self.elapsed.saturating_sub(self.fps_window.0);
+        if window >= Duration::from_secs(1) {
+            self.fps = self.fps_window.1 as f32 / window.as_secs_f32();
+            self.fps_window = (self.elapsed, 0)
+        }
+
+        if self.fixed_timestep.is_zero() { return 0 }
+        self.fixed_accumulator += self.delta;
+        let mut steps = 0;
+        while self.fixed_accumulator >= self.fixed_timestep {
+            self.fixed_accumulator -= self.fixed_timestep;
+            steps += 1;
+            if steps == MAX_FIXED_STEPS {
+                self.fixed_accumulator = Duration::ZERO;
+                break
+            }
+        }
+        steps
+    }
+
+    pub fn fixed_alpha(&self) -> f32 {
+        if self.fixed_timestep.is_zero() { return 0. }
+        self.fixed_accumulator.as_secs_f32() / self.fixed_timestep.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timer() -> (FrameTimer, FakeClock) {
+        let clock = FakeClock::new();
+        let mut timer = FrameTimer::default();
+        timer.set_clock(Box::new(clock.clone()));
+        timer.fixed_timestep = Duration::from_millis(10);
+        (timer, clock)
+    }
+
+    #[test]
+    fn frames() {
+        let (mut timer, clock) = timer();
+        clock.set(Duration::from_secs(5));
+        timer.begin_frame();
+        assert_eq!((timer.frame, timer.delta, timer.elapsed), (0, Duration::ZERO, Duration::ZERO));
+        for (advance, frame, elapsed) in [(16, 1, 16), (4, 2, 20), (0, 3, 20), (30, 4, 50)] {
+            clock.advance(Duration::from_millis(advance));
+            timer.begin_frame();
+            assert_eq!(timer.frame, frame);
+            assert_eq!(timer.delta, Duration::from_millis(advance));
+            assert_eq!(timer.elapsed, Duration::from_millis(elapsed));
+        }
+    }
+
+    #[test]
+    fn fps() {
+        let (mut timer, clock) = timer();
+        timer.begin_frame();
+        for _ in 0..49 {
+            clock.advance(Duration::from_millis(20));
+            timer.begin_frame();
+        }
+        assert_eq!(timer.fps, 0.);
+        clock.advance(Duration::from_millis(20));
+        timer.begin_frame();
+        assert_eq!(timer.fps, 51.);
+        for _ in 0..10 {
+            clock.advance(Duration::from_millis(100));
+            timer.begin_frame();
+        }
+        assert_eq!(timer.fps, 10.);
+    }
+
+    #[test]
+    fn fixed_steps() {
+        let (mut timer, clock) = timer();
+        timer.begin_frame();
+        for (advance, steps, alpha) in [(4, 0, 0.4), (4, 0, 0.8), (4, 1, 0.2), (25, 2, 0.7), (3, 1, 0.)] {
+            clock.advance(Duration::from_millis(advance));
+            assert_eq!(timer.begin_frame(), steps, "{advance}ms");
+            assert!((timer.fixed_alpha() - alpha).abs() < 1e-4, "{advance}ms");
+        }
+        timer.fixed_timestep = Duration::ZERO;
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(timer.begin_frame(), 0);
+        assert_eq!(timer.fixed_alpha(), 0.);
+    }
+
+    #[test]
+    fn fixed_steps_are_clamped() {
+        let (mut timer, clock) = timer();
+        timer.begin_frame();
+        clock.advance(Duration::from_millis(10 * MAX_FIXED_STEPS as u64 + 55));
+        assert_eq!(timer.begin_frame(), MAX_FIXED_STEPS);
+        // The backlog is dropped rather than replayed over the next frames.
+        assert_eq!(timer.fixed_alpha(), 0.);
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(timer.begin_frame(), 1);
+    }
+}
@@ -1,26 +1,36 @@
 use std::{sync::{Arc, Mutex}, fmt::Debug};
 
-use super::Color;
+use super::{Color, Changes};
+use crate::Svg;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub enum BackgroundType {
     Solid(Color),
+    Svg(Arc<Svg>),
+    #[default]
     None
 }
-impl Default for BackgroundType {
-    fn default() -> Self {
-        Self::None
+impl From<Color> for BackgroundType {
+    fn from(color: Color) -> Self {
+        Self::Solid(color)
     }
 }
 
 #[derive(Clone, Default)]
-pub struct Background(Arc<Mutex<BackgroundType>>);
+pub struct Background(Arc<Mutex<BackgroundType>>, Changes);
 impl Background {
     pub fn get(&self) -> BackgroundType {
-        *self.0.lock().unwrap()
+        self.0.lock().unwrap().clone()
     }
     pub fn set(&self, v: BackgroundType) {
-        *self.0.lock().unwrap() = v
+        let mut current = self.0.lock().unwrap();
+        if *current != v {
+            *current = v;
+            self.1.mark()
+        }
+    }
+    pub(crate) fn changes(&self) -> &Changes {
+        &self.1
     }
 }
 impl Debug for Background {
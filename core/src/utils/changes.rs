@@ -0,0 +1,79 @@
+use std::{sync::{Arc, Weak, Mutex, atomic::{AtomicU64, Ordering::Relaxed}}, fmt::Debug};
+
+#[derive(Default)]
+struct Counter {
+    version: AtomicU64,
+    parent: Mutex<Weak<Counter>>
+}
+
+// A version counter that also bumps the counters it is linked under, so a change to a style value
+// reaches the root element of its tree and only the container or context owning that tree redraws.
+#[derive(Clone, Default)]
+pub(crate) struct Changes(Arc<Counter>);
+impl Changes {
+    pub(crate) fn mark(&self) {
+        let mut current = Some(self.0.clone());
+        while let Some(counter) = current {
+            counter.version.fetch_add(1, Relaxed);
+            current = counter.parent.lock().unwrap().upgrade()
+        }
+    }
+    pub(crate) fn version(&self) -> u64 {
+        self.0.version.load(Relaxed)
+    }
+    pub(crate) fn set_parent(&self, parent: Option<&Changes>) {
+        *self.0.parent.lock().unwrap() = parent.map_or_else(Weak::new, |p| Arc::downgrade(&p.0))
+    }
+}
+impl Debug for Changes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.version())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn propagation() {
+        let (root, child, other) = (Changes::default(), Changes::default(), Changes::default());
+        child.set_parent(Some(&root));
+        child.mark();
+        assert_eq!((root.version(), child.version()), (1, 1));
+        other.mark();
+        assert_eq!(root.version(), 1);
+        child.set_parent(None);
+        child.mark();
+        assert_eq!((root.version(), child.version()), (1, 2));
+    }
+
+    #[test]
+    fn element_tree() {
+        let (root, other) = (crate::Element::default(), crate::Element::default());
+        let child = root.create_child();
+        let version = root.get_version();
+        child.style.width.set_px(10.);
+        assert!(root.get_version() > version);
+        let version = (root.get_version(), other.get_version());
+        other.add_child(child.clone());
+        child.style.opacity.set(0.5);
+        assert_eq!(root.get_version(), version.0 + 1);
+        assert!(other.get_version() > version.1 + 1);
+    }
+
+    #[test]
+    fn detached_elements() {
+        let root = crate::Element::default();
+        let (a, b) = (root.create_child(), root.create_child());
+        let c = crate::Element::default();
+        root.replace_child(c.clone(), &a);
+        root.remove_children();
+        let version = root.get_version();
+        for e in [&a, &b, &c] {
+            assert!(e.get_parent().is_none());
+            e.style.opacity.set(0.5);
+            assert_eq!(root.get_version(), version)
+        }
+    }
+}
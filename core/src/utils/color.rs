@@ -1,4 +1,4 @@
-#[derive(Copy, Clone, Default, Debug)]
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -19,19 +19,19 @@ impl Color {
     }
 }
 
-impl Into<Color> for (f32, f32, f32, f32) {
-    fn into(self) -> Color {
-        Color { r: self.0, g: self.1, b: self.2, a: self.3 }
+impl From<(f32, f32, f32, f32)> for Color {
+    fn from(v: (f32, f32, f32, f32)) -> Self {
+        Self { r: v.0, g: v.1, b: v.2, a: v.3 }
     }
 }
 
-impl Into<[f32;4]> for Color {
-    fn into(self) -> [f32;4] {
-        [self.r, self.g, self.b, self.a]
+impl From<Color> for [f32;4] {
+    fn from(c: Color) -> Self {
+        [c.r, c.g, c.b, c.a]
     }
 }
-impl Into<Vec<f32>> for Color {
-    fn into(self) -> Vec<f32> {
-        vec![self.r, self.g, self.b, self.a]
+impl From<Color> for Vec<f32> {
+    fn from(c: Color) -> Self {
+        vec![c.r, c.g, c.b, c.a]
     }
 }
\ No newline at end of file
@@ -1,10 +1,12 @@
-use std::{sync::atomic::{AtomicU64, Ordering::Relaxed}, mem::transmute, fmt::Debug};
+use std::{sync::atomic::{AtomicU64, Ordering::Relaxed}, fmt::Debug};
+
+use super::Changes;
 
 const PX: u64 = 1 << 32;
 const PERC: u64 = 2 << 32;
 const ALL: u64 = 3 << 32;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum CoordValue {
     Px(f32),
     Perc(f32),
@@ -12,30 +14,45 @@ pub enum CoordValue {
 }
 
 #[derive(Default)]
-pub struct Coord(AtomicU64);
+pub struct Coord(AtomicU64, Changes);
 impl Coord {
+    #[inline(always)]
+    fn store(&self, v: u64) {
+        if self.0.swap(v, Relaxed) != v { self.1.mark() }
+    }
     #[inline(always)]
     pub fn set_px(&self, v: f32) {
-        self.0.store(unsafe { transmute::<f32, u32>(v) } as u64 | PX, Relaxed)
+        self.store(v.to_bits() as u64 | PX)
     }
     #[inline(always)]
     pub fn set_perc(&self, v: f32) {
-        self.0.store(unsafe { transmute::<f32, u32>(v) } as u64 | PERC, Relaxed)
+        self.store(v.to_bits() as u64 | PERC)
     }
     #[inline(always)]
     pub fn set_auto(&self) {
-        self.0.store(0, Relaxed)
+        self.store(0)
+    }
+    #[inline(always)]
+    pub fn set(&self, v: CoordValue) {
+        match v {
+            CoordValue::Px(v) => self.set_px(v),
+            CoordValue::Perc(v) => self.set_perc(v),
+            CoordValue::Auto => self.set_auto()
+        }
     }
     #[inline(always)]
     pub fn get(&self) -> CoordValue {
         let v = self.0.load(Relaxed);
         match v & ALL {
             0 => CoordValue::Auto,
-            PX => CoordValue::Px(unsafe { transmute(v as u32) }),
-            PERC => CoordValue::Perc(unsafe { transmute(v as u32) }),
+            PX => CoordValue::Px(f32::from_bits(v as u32)),
+            PERC => CoordValue::Perc(f32::from_bits(v as u32)),
             _ => unreachable!()
         }
     }
+    pub(crate) fn changes(&self) -> &Changes {
+        &self.1
+    }
 }
 impl Debug for Coord {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -0,0 +1,82 @@
+use std::{sync::Mutex, fmt::Debug};
+
+use super::{Color, Changes};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Shadow {
+    pub x: f32,
+    pub y: f32,
+    pub blur: f32,
+    pub spread: f32,
+    pub color: Color,
+    pub inset: bool
+}
+impl Shadow {
+    pub fn new(x: f32, y: f32, blur: f32, color: Color) -> Self {
+        Self { x, y, blur, spread: 0., color, inset: false }
+    }
+    pub fn spread(mut self, spread: f32) -> Self {
+        self.spread = spread;
+        self
+    }
+    pub fn inset(mut self) -> Self {
+        self.inset = true;
+        self
+    }
+}
+impl From<Shadow> for Vec<Shadow> {
+    fn from(shadow: Shadow) -> Self {
+        vec![shadow]
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FilterFunction {
+    Blur(f32)
+}
+impl From<FilterFunction> for Vec<FilterFunction> {
+    fn from(f: FilterFunction) -> Self {
+        vec![f]
+    }
+}
+
+pub struct StyleList<T>(Mutex<Vec<T>>, Changes);
+impl<T> Default for StyleList<T> {
+    fn default() -> Self {
+        Self(Mutex::default(), Changes::default())
+    }
+}
+impl<T: Clone + PartialEq> StyleList<T> {
+    pub fn get(&self) -> Vec<T> {
+        self.0.lock().unwrap().clone()
+    }
+    pub fn set(&self, v: Vec<T>) {
+        let mut current = self.0.lock().unwrap();
+        if *current != v {
+            *current = v;
+            self.1.mark()
+        }
+    }
+    pub(crate) fn changes(&self) -> &Changes {
+        &self.1
+    }
+    pub fn clear(&self) {
+        self.set(Vec::new())
+    }
+    pub fn is_none(&self) -> bool {
+        self.0.lock().unwrap().is_empty()
+    }
+}
+impl<T: Debug> Debug for StyleList<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0.lock().unwrap())
+    }
+}
+
+pub type BoxShadow = StyleList<Shadow>;
+pub type Filter = StyleList<FilterFunction>;
+impl Filter {
+    pub fn get_blur(&self) -> f32 {
+        self.0.lock().unwrap().iter().map(|f| match f { FilterFunction::Blur(v) => v * v }).sum::<f32>().sqrt()
+    }
+}
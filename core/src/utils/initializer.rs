@@ -1,43 +1,96 @@
 use futures::executor::block_on;
+use wgpu::{AdapterInfo, DeviceType, PowerPreference};
 use winit::window::Window;
 
-pub fn create_adapter(instance: &wgpu::Instance, surface: &wgpu::Surface) -> wgpu::Adapter {
-    block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::HighPerformance,
-        compatible_surface: Some(&surface),
-        force_fallback_adapter: false
-    })).unwrap();
-    instance.enumerate_adapters(wgpu::Backends::all()).next().unwrap()
+use crate::{AppConfig, Error, SurfaceSettings, PresentMode, ColorSpace, AlphaMode};
+
+pub fn list_adapters(backends: wgpu::Backends) -> Vec<AdapterInfo> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor { backends, ..Default::default() });
+    instance.enumerate_adapters(backends).map(|adapter| adapter.get_info()).collect()
+}
+
+pub fn create_adapter(instance: &wgpu::Instance, surface: &wgpu::Surface, config: &AppConfig) -> Result<wgpu::Adapter, Error> {
+    let suitable = |adapter: &wgpu::Adapter| adapter.is_surface_supported(surface)
+        && adapter.features().contains(config.features)
+        && config.limits.check_limits(&adapter.limits())
+        && (!config.fallback_adapter || adapter.get_info().device_type == DeviceType::Cpu);
+    let requested = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: config.power_preference,
+        compatible_surface: Some(surface),
+        force_fallback_adapter: config.fallback_adapter
+    }));
+    if let Some(adapter) = requested.filter(suitable) { return Ok(adapter) }
+
+    // The preferred adapter may lack the required features or limits while another one has them.
+    let rank = |info: AdapterInfo| match (config.power_preference, info.device_type) {
+        (PowerPreference::HighPerformance, DeviceType::DiscreteGpu) | (PowerPreference::LowPower, DeviceType::IntegratedGpu) => 0,
+        (_, DeviceType::DiscreteGpu | DeviceType::IntegratedGpu) => 1,
+        (_, DeviceType::VirtualGpu) => 2,
+        (_, DeviceType::Cpu) => 3,
+        (_, DeviceType::Other) => 4
+    };
+    instance.enumerate_adapters(config.backends)
+        .filter(suitable)
+        .min_by_key(|adapter| rank(adapter.get_info()))
+        .ok_or_else(|| Error::NoAdapter { available: list_adapters(config.backends) })
 }
 
-pub fn create_device_queue(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+pub fn create_device_queue(adapter: &wgpu::Adapter, config: &AppConfig) -> Result<(wgpu::Device, wgpu::Queue), Error> {
     block_on(adapter.request_device(
         &wgpu::DeviceDescriptor {
-            features: wgpu::Features::empty(),
-            limits: wgpu::Limits::default(),
+            features: config.features,
+            limits: config.limits.clone(),
             label: None
         },
         None
-    )).unwrap()
+    )).map_err(Error::from)
 }
-
 pub fn configure_surface(
     window: &Window,
     device: &wgpu::Device,
     adapter: &wgpu::Adapter,
-    surface: &wgpu::Surface
-) -> wgpu::SurfaceConfiguration {
+    surface: &wgpu::Surface,
+    settings: &SurfaceSettings
+) -> Result<wgpu::SurfaceConfiguration, Error> {
     let size = window.inner_size();
     let surface_cap = surface.get_capabilities(adapter);
+    let srgb = settings.color_space == ColorSpace::Srgb;
+    let format = match surface_cap.formats.iter().find(|format| format.describe().srgb == srgb) {
+        Some(&format) => format,
+        None => {
+            let format = *surface_cap.formats.first().ok_or(Error::IncompatibleSurface)?;
+            log::warn!("No {:?} surface format available, using {:?}", settings.color_space, format);
+            format
+        }
+    };
+    let (present_mode, fallback) = match settings.present_mode {
+        PresentMode::Vsync => (wgpu::PresentMode::AutoVsync, wgpu::PresentMode::AutoVsync),
+        PresentMode::Immediate => (wgpu::PresentMode::Immediate, wgpu::PresentMode::AutoNoVsync),
+        PresentMode::Mailbox => (wgpu::PresentMode::Mailbox, wgpu::PresentMode::AutoVsync)
+    };
+    let present_mode = if present_mode == fallback || surface_cap.present_modes.contains(&present_mode) { present_mode } else {
+        log::warn!("Present mode {:?} is not supported, using {:?}", present_mode, fallback);
+        fallback
+    };
+    let alpha_mode = match settings.alpha_mode {
+        AlphaMode::Opaque => wgpu::CompositeAlphaMode::Opaque,
+        AlphaMode::PreMultiplied => wgpu::CompositeAlphaMode::PreMultiplied,
+        AlphaMode::PostMultiplied => wgpu::CompositeAlphaMode::PostMultiplied
+    };
+    let alpha_mode = if surface_cap.alpha_modes.contains(&alpha_mode) { alpha_mode } else {
+        let fallback = surface_cap.alpha_modes.first().copied().unwrap_or(wgpu::CompositeAlphaMode::Auto);
+        log::warn!("Alpha mode {:?} is not supported, using {:?}", alpha_mode, fallback);
+        fallback
+    };
     let config = wgpu::SurfaceConfiguration {
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-        format: *surface_cap.formats.iter().next().unwrap(),
-        view_formats: surface_cap.formats,
+        format,
+        view_formats: vec![format],
         width: size.width,
         height: size.height,
-        present_mode: wgpu::PresentMode::AutoVsync,
-        alpha_mode: wgpu::CompositeAlphaMode::Opaque
+        present_mode,
+        alpha_mode
     };
     surface.configure(device, &config);
-    config
+    Ok(config)
 }
\ No newline at end of file
@@ -12,8 +12,8 @@ pub fn default_logger_format(buf: &mut Formatter, record: &Record) -> Result<(),
     } else {
         let now = Local::now();
         let timestamp = format!("{:02}:{:02}:{:02}", now.hour(), now.minute(), now.second());
-        let module = match record.module_path() { Some(v)=>v, None=>"" };
-        let line = match record.line() { Some(v)=>v, None=>0 };
+        let module = record.module_path().unwrap_or_default();
+        let line = record.line().unwrap_or_default();
         let styled_level = buf.default_styled_level(level);
         if level == Level::Error {
             writeln!(buf, "\x1b[90m{timestamp} {styled_level} \x1b[96m{module}:{line}\x1b[0m {args}\n{}",
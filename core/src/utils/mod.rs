@@ -6,6 +6,12 @@ mod initializer;  pub use initializer::*;
 mod coord;        pub use coord::*;
 mod background;   pub use background::*;
 mod position;     pub use position::*;
+mod opacity;      pub use opacity::*;
+mod rect;         pub use rect::*;
+mod transform;    pub use transform::*;
+mod effects;      pub use effects::*;
+mod changes;      pub(crate) use changes::*;
+mod render_target; pub(crate) use render_target::*;
 
 #[cfg(feature = "screenshot")]
 mod screenshot;
@@ -15,8 +21,8 @@ pub struct WindowSize {
     pub width: f32,
     pub height: f32
 }
-impl Into<WindowSize> for PhysicalSize<u32> {
-    fn into(self) -> WindowSize {
-        WindowSize { width: self.width as f32, height: self.height as f32 }
+impl From<PhysicalSize<u32>> for WindowSize {
+    fn from(size: PhysicalSize<u32>) -> Self {
+        Self { width: size.width as f32, height: size.height as f32 }
     }
 }
\ No newline at end of file
@@ -0,0 +1,27 @@
+use std::{sync::atomic::{AtomicU32, Ordering::Relaxed}, fmt::Debug};
+
+use super::Changes;
+
+pub struct Opacity(AtomicU32, Changes);
+impl Default for Opacity {
+    fn default() -> Self {
+        Self(AtomicU32::new(1f32.to_bits()), Changes::default())
+    }
+}
+impl Opacity {
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Relaxed))
+    }
+    pub fn set(&self, v: f32) {
+        let v = v.clamp(0., 1.).to_bits();
+        if self.0.swap(v, Relaxed) != v { self.1.mark() }
+    }
+    pub(crate) fn changes(&self) -> &Changes {
+        &self.1
+    }
+}
+impl Debug for Opacity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.get())
+    }
+}
@@ -1,7 +1,9 @@
 use std::{sync::{Arc, atomic::AtomicBool}, fmt::Debug};
 
+use super::Changes;
+
 #[derive(Clone, Default)]
-pub struct Position(Arc<AtomicBool>);
+pub struct Position(Arc<AtomicBool>, Changes);
 impl Position {
     pub fn is_absolute(&self) -> bool {
         self.0.load(std::sync::atomic::Ordering::Relaxed)
@@ -10,10 +12,13 @@ impl Position {
         !self.0.load(std::sync::atomic::Ordering::Relaxed)
     }
     pub fn set_absolute(&self) {
-        self.0.store(true, std::sync::atomic::Ordering::Relaxed)
+        if !self.0.swap(true, std::sync::atomic::Ordering::Relaxed) { self.1.mark() }
     }
     pub fn set_relative(&self) {
-        self.0.store(false, std::sync::atomic::Ordering::Relaxed)
+        if self.0.swap(false, std::sync::atomic::Ordering::Relaxed) { self.1.mark() }
+    }
+    pub(crate) fn changes(&self) -> &Changes {
+        &self.1
     }
 }
 impl Debug for Position {
@@ -0,0 +1,95 @@
+use wgpu::{Device, RenderPipeline, TextureFormat, Texture, TextureView, BindGroup, BindGroupLayout, CommandEncoder};
+
+use super::Rect;
+
+pub(crate) struct RenderTarget {
+    pub texture: Texture,
+    pub view: TextureView,
+    pub bind_group: BindGroup
+}
+impl RenderTarget {
+    pub fn new(device: &Device, layout: &BindGroupLayout, format: TextureFormat, width: u32, height: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d { width: width.max(1), height: height.max(1), depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[]
+        });
+        let view = texture.create_view(&Default::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view)
+            }]
+        });
+        Self { texture, view, bind_group }
+    }
+}
+
+pub(crate) const CANVAS: usize = 0;
+pub(crate) const TEMP: usize = 1;
+pub(crate) const BACKDROP: usize = 2;
+
+// The canvas, a scratch texture for separable blurs, the blurred backdrop, then one texture per nested layer.
+pub(crate) struct RenderTargets {
+    pub format: TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    targets: Vec<RenderTarget>
+}
+impl RenderTargets {
+    pub fn new(device: &Device, layout: &BindGroupLayout, format: TextureFormat, width: u32, height: u32) -> Self {
+        let mut res = Self { format, width, height, targets: Vec::new() };
+        res.reserve(device, layout, 0);
+        res
+    }
+    pub fn layer(depth: usize) -> usize {
+        if depth == 0 { CANVAS } else { BACKDROP + depth }
+    }
+    pub fn reserve(&mut self, device: &Device, layout: &BindGroupLayout, depth: usize) {
+        while self.targets.len() <= Self::layer(depth).max(BACKDROP) {
+            self.targets.push(RenderTarget::new(device, layout, self.format, self.width, self.height))
+        }
+    }
+    pub fn get(&self, i: usize) -> &RenderTarget {
+        &self.targets[i]
+    }
+}
+
+pub(crate) fn render_pass(
+    encoder: &mut CommandEncoder,
+    target: &TextureView,
+    clear: bool,
+    pipeline: &RenderPipeline,
+    bind_groups: &[&BindGroup],
+    instance: u32,
+    clip: Option<Rect>
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: if clear { wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT) } else { wgpu::LoadOp::Load },
+                store: true
+            }
+        })],
+        depth_stencil_attachment: None
+    });
+    if let Some(clip) = clip {
+        let (x, y) = (clip.x0.max(0.).floor(), clip.y0.max(0.).floor());
+        render_pass.set_scissor_rect(x as u32, y as u32, (clip.x1.ceil() - x) as u32, (clip.y1.ceil() - y) as u32);
+    }
+    render_pass.set_pipeline(pipeline);
+    for (i, bind_group) in bind_groups.iter().enumerate() {
+        render_pass.set_bind_group(i as u32, bind_group, &[]);
+    }
+    render_pass.draw(0..6, instance..instance + 1);
+}
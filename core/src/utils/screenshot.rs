@@ -1,32 +1,35 @@
 use std::{num::NonZeroU32, sync::mpsc::channel};
-use image::{ImageBuffer, Rgba};
+use image::{ImageBuffer, ImageError, Rgba, error::{ParameterError, ParameterErrorKind}};
 
-use crate::Context;
-
-const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+use crate::{Context, Error, RenderTarget, RenderTargets, render_pass, CANVAS};
 
 impl Context {
     pub fn screenshot(&self, width: u32, height: u32) {
-        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
-            view_formats: &[FORMAT],
-            size: wgpu::Extent3d {
-                width,
-                height,
-                depth_or_array_layers: 1
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: FORMAT,
-            usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::RENDER_ATTACHMENT,
-            label: None
-        });
-        let texture_view = texture.create_view(&Default::default());
+        if let Err(e) = self.try_screenshot(width, height) {
+            log::error!("{}", e)
+        }
+    }
+    pub fn try_screenshot(&self, width: u32, height: u32) -> Result<(), Error> {
+        let invalid_size = Error::InvalidSize { width, height };
+        let (rows, limit) = match NonZeroU32::new(height) {
+            Some(v) => (v, self.device.limits().max_texture_dimension_2d),
+            None => return Err(invalid_size)
+        };
+        if width == 0 || width > limit || height > limit { return Err(invalid_size) }
+        // Rendered into a fixed format so the readback doesn't depend on the surface format.
+        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        let pipelines = self.pipelines_for(format);
+        let mut targets = RenderTargets::new(&self.device, &pipelines.texture_layout, format, width, height);
+        let output = RenderTarget::new(&self.device, &pipelines.texture_layout, format, width, height);
 
         let u32_size = std::mem::size_of::<u32>() as u32;
+        // Rows of a texture copy must start at a multiple of the alignment, the padding is dropped on readback.
+        let row_size = u32_size * width;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row_size = (row_size + align - 1) / align * align;
 
         let output_buffer_desc = wgpu::BufferDescriptor {
-            size: (u32_size * width * height) as wgpu::BufferAddress,
+            size: padded_row_size as wgpu::BufferAddress * height as wgpu::BufferAddress,
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
             label: None,
             mapped_at_creation: false
@@ -37,13 +40,16 @@ impl Context {
 
         let containers = self.containers.lock().unwrap().clone();
         for container in containers {
-            container.render(self, &mut encoder, &texture_view, false);
+            container.render(self, &mut encoder, &mut targets, None);
         }
-        
+        // The canvas is premultiplied, PNG expects straight alpha.
+        render_pass(&mut encoder, &output.view, true, &pipelines.unpremultiply, &[&targets.get(CANVAS).bind_group], 0, None);
+        let texture = &output.texture;
+
         encoder.copy_texture_to_buffer(
             wgpu::ImageCopyTexture {
                 aspect: wgpu::TextureAspect::All,
-                texture: &texture,
+                texture,
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO
             },
@@ -51,8 +57,8 @@ impl Context {
                 buffer: &output_buffer,
                 layout: wgpu::ImageDataLayout {
                     offset: 0,
-                    bytes_per_row: Some(NonZeroU32::new(u32_size * width).unwrap()),
-                    rows_per_image: Some(NonZeroU32::new(height).unwrap())
+                    bytes_per_row: NonZeroU32::new(padded_row_size),
+                    rows_per_image: Some(rows)
                 }
             },
             texture.size()
@@ -65,17 +71,24 @@ impl Context {
         
             let (tx, rx) = channel();
             buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
-                tx.send(result).unwrap();
+                // The receiver only goes away if the wait below was abandoned.
+                let _ = tx.send(result);
             });
             self.device.poll(wgpu::Maintain::Wait);
-            rx.recv().unwrap().unwrap();
-        
-            let mut data: Vec<u8> = buffer_slice.get_mapped_range().to_vec();
-            data.reverse();
+            // The callback is dropped without running when the device is lost.
+            rx.recv().map_err(|_| wgpu::BufferAsyncError)??;
+
+            let data: Vec<u8> = buffer_slice.get_mapped_range()
+                .chunks(padded_row_size as usize)
+                .flat_map(|row| &row[..row_size as usize])
+                .copied()
+                .collect();
 
-            let buffer = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, data).unwrap();
-            buffer.save("image.png").unwrap();
+            let buffer = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, data)
+                .ok_or_else(|| ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::DimensionMismatch)))?;
+            buffer.save("image.png")?;
         }
         output_buffer.unmap();
+        Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,164 @@
+use std::{sync::Mutex, fmt::Debug};
+
+use super::{CoordValue, Changes};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Matrix {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32
+}
+impl Default for Matrix {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+impl Matrix {
+    pub const IDENTITY: Self = Self::new(1., 0., 0., 1., 0., 0.);
+    pub const fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Self { a, b, c, d, e, f }
+    }
+    pub fn translate(x: f32, y: f32) -> Self {
+        Self::new(1., 0., 0., 1., x, y)
+    }
+    pub fn scale(x: f32, y: f32) -> Self {
+        Self::new(x, 0., 0., y, 0., 0.)
+    }
+    pub fn rotate(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin, -sin, cos, 0., 0.)
+    }
+    pub fn skew(x: f32, y: f32) -> Self {
+        Self::new(1., y.tan(), x.tan(), 1., 0., 0.)
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+    pub fn multiply(&self, o: &Self) -> Self {
+        Self::new(
+            self.a * o.a + self.c * o.b,
+            self.b * o.a + self.d * o.b,
+            self.a * o.c + self.c * o.d,
+            self.b * o.c + self.d * o.d,
+            self.a * o.e + self.c * o.f + self.e,
+            self.b * o.e + self.d * o.f + self.f
+        )
+    }
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() < 1e-12 || !det.is_finite() { return None }
+        Some(Self::new(
+            self.d / det,
+            -self.b / det,
+            -self.c / det,
+            self.a / det,
+            (self.c * self.f - self.d * self.e) / det,
+            (self.b * self.e - self.a * self.f) / det
+        ))
+    }
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.a * x + self.c * y + self.e, self.b * x + self.d * y + self.f)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TransformFunction {
+    Translate(CoordValue, CoordValue),
+    Scale(f32, f32),
+    Rotate(f32),
+    Skew(f32, f32),
+    Matrix(Matrix)
+}
+impl TransformFunction {
+    pub fn to_matrix(&self, width: f32, height: f32) -> Matrix {
+        let resolve = |v: CoordValue, size: f32| match v {
+            CoordValue::Px(v) => v,
+            CoordValue::Perc(v) => v * size,
+            CoordValue::Auto => 0.
+        };
+        match *self {
+            Self::Translate(x, y) => Matrix::translate(resolve(x, width), resolve(y, height)),
+            Self::Scale(x, y) => Matrix::scale(x, y),
+            Self::Rotate(angle) => Matrix::rotate(angle),
+            Self::Skew(x, y) => Matrix::skew(x, y),
+            Self::Matrix(m) => m
+        }
+    }
+}
+impl From<Matrix> for TransformFunction {
+    fn from(m: Matrix) -> Self {
+        Self::Matrix(m)
+    }
+}
+impl From<TransformFunction> for Vec<TransformFunction> {
+    fn from(f: TransformFunction) -> Self {
+        vec![f]
+    }
+}
+
+pub fn transform_matrix(functions: &[TransformFunction], width: f32, height: f32) -> Matrix {
+    functions.iter().fold(Matrix::IDENTITY, |m, f| m.multiply(&f.to_matrix(width, height)))
+}
+
+#[derive(Default)]
+pub struct Transform(Mutex<Vec<TransformFunction>>, Changes);
+impl Transform {
+    pub fn get(&self) -> Vec<TransformFunction> {
+        self.0.lock().unwrap().clone()
+    }
+    pub fn set(&self, v: Vec<TransformFunction>) {
+        let mut current = self.0.lock().unwrap();
+        if *current != v {
+            *current = v;
+            self.1.mark()
+        }
+    }
+    pub(crate) fn changes(&self) -> &Changes {
+        &self.1
+    }
+    pub fn clear(&self) {
+        self.set(Vec::new())
+    }
+    pub fn is_none(&self) -> bool {
+        self.0.lock().unwrap().is_empty()
+    }
+    pub fn get_matrix(&self, width: f32, height: f32) -> Matrix {
+        transform_matrix(&self.0.lock().unwrap(), width, height)
+    }
+}
+impl Debug for Transform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.get())
+    }
+}
+
+pub struct TransformOrigin(Mutex<(CoordValue, CoordValue)>, Changes);
+impl Default for TransformOrigin {
+    fn default() -> Self {
+        Self(Mutex::new((CoordValue::Perc(0.5), CoordValue::Perc(0.5))), Changes::default())
+    }
+}
+impl TransformOrigin {
+    pub fn get(&self) -> (CoordValue, CoordValue) {
+        *self.0.lock().unwrap()
+    }
+    pub fn set(&self, v: (CoordValue, CoordValue)) {
+        let mut current = self.0.lock().unwrap();
+        if *current != v {
+            *current = v;
+            self.1.mark()
+        }
+    }
+    pub(crate) fn changes(&self) -> &Changes {
+        &self.1
+    }
+}
+impl Debug for TransformOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.get())
+    }
+}
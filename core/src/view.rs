@@ -0,0 +1,45 @@
+#[doc(hidden)]
+pub use oscillo_macros::view as __view;
+
+// Forwards to the proc macro with `$crate` as the core path, so the expansion resolves even when
+// this crate is renamed in the caller's manifest.
+#[macro_export]
+macro_rules! view {
+    ($($tokens:tt)*) => { $crate::__view!(crate = $crate; $($tokens)*) }
+}
+
+use crate::Element;
+
+pub trait IntoView {
+    fn append_to(self, parent: &Element);
+}
+impl IntoView for Element {
+    fn append_to(self, parent: &Element) {
+        parent.add_child(self)
+    }
+}
+impl IntoView for &Element {
+    fn append_to(self, parent: &Element) {
+        parent.add_child(self.clone())
+    }
+}
+impl IntoView for &str {
+    fn append_to(self, parent: &Element) {
+        parent.push_text(self)
+    }
+}
+impl IntoView for String {
+    fn append_to(self, parent: &Element) {
+        parent.push_text(&self)
+    }
+}
+impl<T: IntoView> IntoView for Option<T> {
+    fn append_to(self, parent: &Element) {
+        if let Some(v) = self { v.append_to(parent) }
+    }
+}
+impl<T: IntoView> IntoView for Vec<T> {
+    fn append_to(self, parent: &Element) {
+        for v in self { v.append_to(parent) }
+    }
+}
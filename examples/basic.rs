@@ -1,37 +1,37 @@
-use oscillo_core::{Script, Context, Key, App, Color, BackgroundType, Element};
+use oscillo_core::{Script, Context, Key, App, Color, BackgroundType, Element, view};
 
 pub struct MainScript {
     root: Element
 }
 impl Script for MainScript {
-    fn setup(&self, _: Context) {
+    fn setup(&mut self, _: Context) {
         self.root.style.background.set(BackgroundType::Solid(Color::RED));
-        {
-            let c = self.root.create_child();
-            c.style.background.set(BackgroundType::Solid(Color::GREEN));
-            c.style.width.set_px(100.);
-            c.style.height.set_px(50.);
-            c.style.x.set_px(150.);
-            c.style.y.set_px(10.);
-        }
-        {
-            let c = self.root.create_child();
-            c.style.background.set(BackgroundType::Solid(Color::YELLOW));
-            c.style.width.set_perc(0.5);
-            c.style.height.set_perc(0.5);
-            c.style.x.set_perc(0.25);
-            c.style.y.set_perc(0.25);
-            {
-                let c = c.create_child();
-                c.style.background.set(BackgroundType::Solid(Color::GREEN));
-                c.style.width.set_perc(0.5);
-                c.style.height.set_perc(0.5);
-                c.style.x.set_px(-10.);
-                c.style.y.set_perc(0.25);
+        view!(self.root =>
+            div {
+                background: Color::GREEN,
+                width: 100px,
+                height: 50px,
+                x: 150px,
+                y: 10px,
+                on_click: |_, e| e.target.style.background.set(BackgroundType::Solid(Color::BLUE))
             }
-        }
+            div {
+                background: Color::YELLOW,
+                width: 50%,
+                height: 50%,
+                x: 25%,
+                y: 25%,
+                div {
+                    background: Color::GREEN,
+                    width: 50%,
+                    height: 50%,
+                    x: -10px,
+                    y: 25%
+                }
+            }
+        );
     }
-    fn update(&self, c: Context) {
+    fn update(&mut self, c: Context) {
         if c.is_key_pressed(&Key::Escape) {
             c.exit()
         }
@@ -43,11 +43,10 @@ impl Script for MainScript {
 
 fn main() {
     oscillo_core::logger().init();
-    let app = App::new();
-    app.context.window.set_title("Oscillo basic example");
+    let app = App::builder().title("Oscillo basic example").build();
     let container = app.context.new_container();
     container.add_script(MainScript {
         root: container.root.clone()
     });
     app.run()
-}
\ No newline at end of file
+}
@@ -0,0 +1,366 @@
+use proc_macro2::{TokenStream, TokenTree, Spacing, Span, Literal};
+use quote::{quote, format_ident};
+use syn::{
+    Expr, Ident, LitStr, Lit, Token, braced, parenthesized,
+    parse::{Parse, ParseStream, discouraged::Speculative}, ext::IdentExt
+};
+
+enum StyleValue {
+    Px(f32),
+    Perc(f32),
+    Auto,
+    Absolute,
+    Relative,
+    Expr(Expr)
+}
+
+enum Item {
+    Node(Node),
+    Style(Ident, StyleValue),
+    Event(Ident, Expr),
+    Text(LitStr),
+    Expr(Expr)
+}
+
+struct Node {
+    tag: Ident,
+    attributes: Vec<(String, Expr)>,
+    items: Vec<Item>
+}
+
+struct Nodes(Vec<Node>);
+
+fn is_separator(input: ParseStream) -> bool {
+    input.is_empty() || input.peek(Token![,]) || input.peek(Token![;])
+}
+
+fn parse_number(lit: &Lit) -> Option<(f32, String)> {
+    match lit {
+        Lit::Int(v) => Some((v.base10_parse::<i64>().ok()? as f32, v.suffix().to_string())),
+        Lit::Float(v) => Some((v.base10_parse::<f32>().ok()?, v.suffix().to_string())),
+        _ => None
+    }
+}
+
+impl Parse for StyleValue {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let fork = input.fork();
+        let sign = if fork.parse::<Option<Token![-]>>()?.is_some() { -1. } else { 1. };
+        if let Ok(lit) = fork.parse::<Lit>() {
+            if let Some((v, suffix)) = parse_number(&lit) {
+                let v = v * sign;
+                if fork.peek(Token![%]) {
+                    fork.parse::<Token![%]>()?;
+                    if is_separator(&fork) {
+                        input.advance_to(&fork);
+                        return Ok(Self::Perc(v / 100.))
+                    }
+                } else if is_separator(&fork) && (suffix == "px" || suffix.is_empty()) {
+                    input.advance_to(&fork);
+                    return Ok(Self::Px(v))
+                }
+            }
+        }
+        let fork = input.fork();
+        if let Ok(ident) = fork.parse::<Ident>() {
+            if is_separator(&fork) {
+                let keyword = match ident.to_string().as_str() {
+                    "auto" => Some(Self::Auto),
+                    "absolute" => Some(Self::Absolute),
+                    "relative" => Some(Self::Relative),
+                    _ => None
+                };
+                if let Some(v) = keyword {
+                    input.parse::<Ident>()?;
+                    return Ok(v)
+                }
+            }
+        }
+        Ok(Self::Expr(input.parse()?))
+    }
+}
+
+impl Parse for Item {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            return Ok(Self::Text(input.parse()?))
+        }
+        if input.peek(syn::token::Brace) {
+            let content;
+            braced!(content in input);
+            return Ok(Self::Expr(content.parse()?))
+        }
+        if input.peek(Ident::peek_any) && input.peek2(Token![:]) && !input.peek2(Token![::]) {
+            let name = Ident::parse_any(input)?;
+            input.parse::<Token![:]>()?;
+            return Ok(if name.to_string().starts_with("on_") {
+                Self::Event(name, input.parse()?)
+            } else {
+                Self::Style(name, input.parse()?)
+            })
+        }
+        Ok(Self::Node(input.parse()?))
+    }
+}
+
+fn parse_attribute_name(input: ParseStream) -> syn::Result<String> {
+    let mut name = Ident::parse_any(input)?.to_string();
+    while input.peek(Token![-]) {
+        input.parse::<Token![-]>()?;
+        name.push('-');
+        name.push_str(&Ident::parse_any(input)?.to_string());
+    }
+    Ok(name)
+}
+
+impl Parse for Node {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let tag = Ident::parse_any(input)?;
+        let mut attributes = Vec::new();
+        if input.peek(syn::token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            while !content.is_empty() {
+                let name = parse_attribute_name(&content)?;
+                content.parse::<Token![=]>()?;
+                attributes.push((name, content.parse()?));
+                if !content.is_empty() { content.parse::<Token![,]>()?; }
+            }
+        }
+        let mut items = Vec::new();
+        if input.peek(syn::token::Brace) {
+            let content;
+            braced!(content in input);
+            while !content.is_empty() {
+                items.push(content.parse()?);
+                while content.peek(Token![,]) || content.peek(Token![;]) {
+                    content.parse::<TokenTree>()?;
+                }
+            }
+        }
+        Ok(Self { tag, attributes, items })
+    }
+}
+
+impl Parse for Nodes {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut nodes = Vec::new();
+        while !input.is_empty() {
+            nodes.push(input.parse()?);
+            while input.peek(Token![,]) || input.peek(Token![;]) {
+                input.parse::<TokenTree>()?;
+            }
+        }
+        Ok(Self(nodes))
+    }
+}
+
+fn expand_node(node: &Node, var: &Ident, krate: &TokenStream, counter: &mut usize) -> syn::Result<TokenStream> {
+    let tag = node.tag.to_string();
+    let mut res = quote! { #var.set_tag(#tag); };
+    for (name, value) in node.attributes.iter() {
+        res.extend(quote! {
+            #var.set_attribute(#name, &::std::string::ToString::to_string(&(#value)));
+        })
+    }
+    for item in node.items.iter() {
+        res.extend(match item {
+            Item::Node(child) => {
+                *counter += 1;
+                let child_var = format_ident!("__oscillo_element_{}", *counter, span = Span::mixed_site());
+                let body = expand_node(child, &child_var, krate, counter)?;
+                quote! {
+                    {
+                        let #child_var = #var.create_child();
+                        #body
+                    }
+                }
+            }
+            Item::Style(name, value) => {
+                let v = |v: f32| Literal::f32_suffixed(v);
+                match value {
+                    StyleValue::Px(px) => { let px = v(*px); quote! { #var.style.#name.set_px(#px); } }
+                    StyleValue::Perc(perc) => { let perc = v(*perc); quote! { #var.style.#name.set_perc(#perc); } }
+                    StyleValue::Auto => quote! { #var.style.#name.set_auto(); },
+                    StyleValue::Absolute => quote! { #var.style.#name.set_absolute(); },
+                    StyleValue::Relative => quote! { #var.style.#name.set_relative(); },
+                    StyleValue::Expr(e) => quote! { #var.style.#name.set(::core::convert::Into::into(#e)); }
+                }
+            }
+            Item::Event(name, handler) => {
+                let kind = match name.to_string().as_str() {
+                    "on_click" => quote! { Click },
+                    "on_mouse_down" => quote! { MouseDown },
+                    "on_mouse_up" => quote! { MouseUp },
+                    "on_mouse_move" => quote! { MouseMove },
+                    _ => return Err(syn::Error::new(name.span(), format!("unknown event handler `{name}`")))
+                };
+                quote! { #var.add_event_listener(#krate::EventType::#kind, #handler); }
+            }
+            Item::Text(text) => quote! { #krate::IntoView::append_to(#text, &#var); },
+            Item::Expr(e) => quote! { #krate::IntoView::append_to(#e, &#var); }
+        })
+    }
+    Ok(res)
+}
+
+fn split_parent(input: TokenStream) -> (Option<TokenStream>, TokenStream) {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    for (i, pair) in tokens.windows(2).enumerate() {
+        if let (TokenTree::Punct(a), TokenTree::Punct(b)) = (&pair[0], &pair[1]) {
+            if a.as_char() == '=' && a.spacing() == Spacing::Joint && b.as_char() == '>' {
+                let parent = tokens[..i].iter().cloned().collect();
+                let nodes = tokens[i + 2..].iter().cloned().collect();
+                return (Some(parent), nodes)
+            }
+        }
+    }
+    (None, tokens.into_iter().collect())
+}
+
+// `crate = path;` at the start of the input overrides the path of the core crate.
+fn split_crate(input: TokenStream) -> (Option<TokenStream>, TokenStream) {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    if let [TokenTree::Ident(name), TokenTree::Punct(eq), ..] = tokens.as_slice() {
+        let end = tokens.iter().position(|t| matches!(t, TokenTree::Punct(p) if p.as_char() == ';'));
+        if name == "crate" && eq.as_char() == '=' && eq.spacing() == Spacing::Alone {
+            if let Some(end) = end {
+                return (Some(tokens[2..end].iter().cloned().collect()), tokens[end + 1..].iter().cloned().collect())
+            }
+        }
+    }
+    (None, tokens.into_iter().collect())
+}
+
+fn expand(input: TokenStream) -> syn::Result<TokenStream> {
+    let (krate, input) = split_crate(input);
+    let krate = krate.unwrap_or_else(|| quote! { ::oscillo_core });
+    let (parent, nodes) = split_parent(input);
+    let Nodes(nodes) = syn::parse2(nodes)?;
+    let mut counter = 0;
+    match parent {
+        Some(parent) => {
+            let parent_var = Ident::new("__oscillo_parent", Span::mixed_site());
+            let mut res = TokenStream::new();
+            for node in nodes.iter() {
+                counter += 1;
+                let var = format_ident!("__oscillo_element_{}", counter, span = Span::mixed_site());
+                let body = expand_node(node, &var, &krate, &mut counter)?;
+                res.extend(quote! {
+                    {
+                        let #var = #parent_var.create_child();
+                        #body
+                    }
+                })
+            }
+            Ok(quote! {
+                {
+                    let #parent_var: &#krate::Element = &(#parent);
+                    #res
+                }
+            })
+        }
+        None => {
+            let node = match nodes.as_slice() {
+                [node] => node,
+                _ => return Err(syn::Error::new(Span::call_site(), "expected exactly one root element, use `view!(parent => ...)` to create several"))
+            };
+            let var = Ident::new("__oscillo_root", Span::mixed_site());
+            let body = expand_node(node, &var, &krate, &mut counter)?;
+            Ok(quote! {
+                {
+                    let #var = #krate::Element::default();
+                    #body
+                    #var
+                }
+            })
+        }
+    }
+}
+
+#[proc_macro]
+pub fn view(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    match expand(input.into()) {
+        Ok(v) => v.into(),
+        Err(e) => e.to_compile_error().into()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_expands(input: TokenStream, expected: TokenStream) {
+        let res: Expr = syn::parse2(expand(input).unwrap()).unwrap();
+        let expected: Expr = syn::parse2(expected).unwrap();
+        assert_eq!(res, expected, "{}", quote!(#res))
+    }
+
+    #[test]
+    fn root_element() {
+        assert_expands(quote! { div(id = "a") { width: 10px, height: 50%, x: auto, "text" } }, quote! {
+            {
+                let __oscillo_root = ::oscillo_core::Element::default();
+                __oscillo_root.set_tag("div");
+                __oscillo_root.set_attribute("id", &::std::string::ToString::to_string(&("a")));
+                __oscillo_root.style.width.set_px(10f32);
+                __oscillo_root.style.height.set_perc(0.5f32);
+                __oscillo_root.style.x.set_auto();
+                ::oscillo_core::IntoView::append_to("text", &__oscillo_root);
+                __oscillo_root
+            }
+        });
+    }
+
+    #[test]
+    fn parent_and_children() {
+        assert_expands(quote! { self.root => div { span {} } p {} }, quote! {
+            {
+                let __oscillo_parent: &::oscillo_core::Element = &(self.root);
+                {
+                    let __oscillo_element_1 = __oscillo_parent.create_child();
+                    __oscillo_element_1.set_tag("div");
+                    {
+                        let __oscillo_element_2 = __oscillo_element_1.create_child();
+                        __oscillo_element_2.set_tag("span");
+                    }
+                }
+                {
+                    let __oscillo_element_3 = __oscillo_parent.create_child();
+                    __oscillo_element_3.set_tag("p");
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn crate_override() {
+        assert_expands(quote! { crate = my::ui; div { on_click: |_, _| {}, { items } } }, quote! {
+            {
+                let __oscillo_root = my::ui::Element::default();
+                __oscillo_root.set_tag("div");
+                __oscillo_root.add_event_listener(my::ui::EventType::Click, |_, _| {});
+                my::ui::IntoView::append_to(items, &__oscillo_root);
+                __oscillo_root
+            }
+        });
+        assert_expands(quote! { crate = crate; e => div {} }, quote! {
+            {
+                let __oscillo_parent: &crate::Element = &(e);
+                {
+                    let __oscillo_element_1 = __oscillo_parent.create_child();
+                    __oscillo_element_1.set_tag("div");
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn errors() {
+        let message = |input: TokenStream| expand(input).err().unwrap().to_string();
+        assert!(message(quote! { div {} p {} }).contains("exactly one root element"));
+        assert!(message(quote! { div { on_hover: |_, _| {} } }).contains("unknown event handler `on_hover`"));
+        assert!(expand(quote! { div(id) {} }).is_err());
+    }
+}